@@ -1,107 +1,162 @@
 extern crate proc_macro;
-use proc_macro::{TokenStream};
-use proc_macro2::{Span};
-use quote::{quote};
-use syn::{parse_macro_input, ItemImpl, ImplItem, ImplItemMethod, ItemTrait, TraitItem, Ident, Block, Visibility, VisPublic, token::Pub};
-//use syn::{ReturnType};
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{
+    parse_macro_input, parse_quote, Error, FnArg, Ident, ItemTrait, Pat, PatIdent, ReturnType,
+    TraitItem, TraitItemMethod,
+};
 
-/// a macro to generate an empty blueprint just to get the stub functions for inter-blueprint calls
+/// a macro to generate a typed wrapper for calling another component, from a trait describing its methods
 /// avoids manually specifying an ABI, and allows a concise definition using trait syntax
+///
+/// ```ignore
+/// #[blueprint_stub]
+/// pub trait Staking {
+///     fn new_user(&mut self) -> Bucket;
+///     fn stake(&mut self, staking: Bucket, auth: Proof);
+///     fn withdraw(&mut self, auth: Proof) -> Bucket;
+///     // default methods are not called on the component, they run locally using the other stubs
+///     fn restake(&self, auth: Proof) {
+///         let staking = self.withdraw(auth.clone());
+///         self.stake(staking, auth);
+///     }
+/// }
+///
+/// let staking: Staking = staking_address.into();
+/// let staking_nft = staking.new_user();
+/// ```
+///
+/// The trait itself is replaced by a struct with the same name which:
+/// * is built from (and converts back to) the `ComponentAddress` of the component to call
+/// * has every trait method as a method calling the component, any return type the component can encode works
+///   (`Decimal`, `Bucket`, `Proof`, tuples, `Option`, ...)
+/// * takes `&self` for every method, even `&mut self` ones, as it only holds the address.  The component itself
+///   still gets mutable access
+/// * can be stored in a component, or passed to and returned from one
+///
+/// Only methods are supported, functions without a `self` receiver can't be called without a package address.
 #[proc_macro_attribute]
 pub fn blueprint_stub(_metadata: TokenStream, input: TokenStream) -> TokenStream {
     // parse the trait
-    let mut input = parse_macro_input!(input as ItemTrait);
-    // grap the identifier
-    let ident = input.ident.clone();
-    // create the skeleton for impl to be generated
-    let the_impl = TokenStream::from(quote! {
+    let input = parse_macro_input!(input as ItemTrait);
+    // grab the identifier, visibility and docs
+    let ident = &input.ident;
+    let vis = &input.vis;
+    let attrs = &input.attrs;
+
+    // iterate over the methods creating the stubs
+    let mut methods = Vec::new();
+    for item in input.items.iter() {
+        let method = match item {
+            TraitItem::Method(method) => stub_method(method),
+            _ => Err(Error::new(
+                item.span(),
+                "blueprint_stub only supports methods",
+            )),
+        };
+        match method {
+            Ok(method) => methods.push(method),
+            Err(error) => return TokenStream::from(error.to_compile_error()),
+        }
+    }
+
+    // create the final output
+    // a plain struct holding the address of the component, so it can be used like the stubs generated by blueprint! or import!
+    TokenStream::from(quote! {
+        #(#attrs)*
+        #[derive(
+            Clone,
+            Copy,
+            Debug,
+            PartialEq,
+            Eq,
+            ::sbor::TypeId,
+            ::sbor::Encode,
+            ::sbor::Decode,
+            ::sbor::Describe
+        )]
+        #vis struct #ident {
+            component_address: ::scrypto::prelude::ComponentAddress,
+        }
+
         #[allow(unused)]
         impl #ident {
+            pub fn component_address(&self) -> ::scrypto::prelude::ComponentAddress {
+                self.component_address
+            }
+
+            #(#methods)*
+        }
 
+        impl From<::scrypto::prelude::ComponentAddress> for #ident {
+            fn from(component_address: ::scrypto::prelude::ComponentAddress) -> Self {
+                Self { component_address }
+            }
         }
-    });
-    let mut the_impl = parse_macro_input!(the_impl as ItemImpl);
-    // iterate over the methods creating the implementation and filling the impl items
-    // let decimal_ident = Ident::new(&format!("Decimal"), Span::call_site()); // used to find Decimal type
-    let items = &mut input.items;
-    for i in 0..items.len() {
-        match items[i] {
-            TraitItem::Method(ref mut method) => {
-                //method.semi_token = None;
-                //method.sig.ident =  Ident::new(&format!("{}_{}", ident, i), Span::call_site());
-                let empty_block = TokenStream::from(quote! {
-                    {
-                        panic!("only exists for stub, do not call");
-                    }
-                });
-                /*
-                let block = match method.sig.output {
-                    ReturnType::Default => { empty_block },
-                    ReturnType::Type(_, ref box_type) => {
-                        let is_decimal: bool = match **box_type {
-                            syn::Type::Path(ref type_path) => {
-                                type_path.path.is_ident(&decimal_ident)
-                            },
-                            _ => false
-                        };
-                        if is_decimal {
-                            TokenStream::from(quote! {
-                                { // block returning the default for Decimal
-                                    0.into()
-                                    //let result: #box_type = Default::default();
-                                    //result
-                                }
-                            })
-                        } else {
-                            TokenStream::from(quote! {
-                                { // block returning the default for the type
-                                    let result: #box_type = Default::default();
-                                    result
-                                }
-                            })
-                        }
-                    }
-                };
-                let block = parse_macro_input!(block as Block);
-                */
-                let block = parse_macro_input!(empty_block as Block);
-                method.default = Some(block.clone());
-                // now update the vec of ImplItem::Method from the TraitItem::Method 
-                the_impl.items.push(
-                    ImplItem::Method(ImplItemMethod {
-                        attrs: the_impl.attrs.clone(), // copy the #[allow(unused)] to each method
-                        vis: Visibility::Public(
-                            VisPublic{
-                                pub_token: Pub{
-                                    span: Span::call_site()
-                                }
-                            }
-                        ),
-                        defaultness: None,
-                        sig: method.sig.clone(),
-                        block: block
-                    }
-                )
 
-                );
-            },
-            _ => {}
+        impl From<#ident> for ::scrypto::prelude::ComponentAddress {
+            fn from(stub: #ident) -> Self {
+                stub.component_address
+            }
+        }
+    })
+}
+
+/// the stub for a single trait method, either calling the component or running the default body
+fn stub_method(method: &TraitItemMethod) -> syn::Result<proc_macro2::TokenStream> {
+    let mut sig = method.sig.clone();
+
+    // the stub only holds the address, so &self is enough for &mut self methods too
+    match sig.inputs.first() {
+        Some(FnArg::Receiver(_)) => {}
+        _ => {
+            return Err(Error::new(
+                sig.span(),
+                "blueprint_stub only supports methods with a self receiver",
+            ))
         }
     }
+    sig.inputs[0] = parse_quote!(&self);
 
-    // create the final output
-    // a blueprint! with the geenerated impl, but all inside a module so the code is not callable
-    // then reexport only the stubs
-    let mod_name = Ident::new(&format!("internal_{}", ident), Span::call_site());
-    TokenStream::from(quote!{
-        mod #mod_name {
-        use super::*;
-        blueprint! {
-            struct #ident {}
-            #the_impl
+    // name every argument so it can be passed along, patterns like `_` or tuples get a generated name
+    let mut args = Vec::new();
+    for (i, input) in sig.inputs.iter_mut().enumerate().skip(1) {
+        if let FnArg::Typed(pat_type) = input {
+            let arg = match &*pat_type.pat {
+                Pat::Ident(PatIdent { ident, .. }) => ident.clone(),
+                _ => {
+                    let arg = Ident::new(&format!("arg{}", i), Span::call_site());
+                    pat_type.pat = parse_quote!(#arg);
+                    arg
+                }
+            };
+            args.push(arg);
         }
+    }
+
+    let attrs = &method.attrs;
+    let block = match &method.default {
+        // default methods run locally, on top of the other stubs
+        Some(block) => quote! { #block },
+        None => {
+            let name = sig.ident.to_string();
+            let output = match &sig.output {
+                ReturnType::Default => quote! { () },
+                ReturnType::Type(_, output) => quote! { #output },
+            };
+            quote! {
+                {
+                    ::scrypto::borrow_component!(self.component_address)
+                        .call::<#output>(#name, ::scrypto::args![#(#args),*])
+                }
+            }
         }
-        // reexport the stub only
-        pub use #mod_name::#ident;
+    };
+
+    Ok(quote! {
+        #(#attrs)*
+        pub #sig #block
     })
-}
\ No newline at end of file
+}
@@ -1,9 +1,9 @@
 use scrypto::prelude::*;
 
-use scrypto_traits::blueprint_stub;
+use bonding_macros::blueprint_stub;
+
+// these only need to compile, calling them needs a running component
 
-// the expected inferface, but declaring a trait doesn't actually matter since it really depends on the ABI, just to be helpful
-// Probably generating ABI / stubs from trait declaration is what is needed instead of depending on an existing implementation as is done below
 #[blueprint_stub]
 pub trait BondingCurve {
     fn get_mint_amount(&self, collateral_amount: Decimal, reserve_amount: Decimal, supply_amount: Decimal) -> Decimal;
@@ -15,4 +15,38 @@ pub trait BondingCurve {
 pub trait TestTrait {
     fn method1(&self);
     fn method2(&self) -> u32;
-}
\ No newline at end of file
+    fn method3(&mut self, bucket: Bucket) -> (Bucket, Option<Bucket>);
+    fn method4(&mut self, _: Proof) -> Proof;
+    // default methods run locally on top of the other stubs
+    fn method5(&self) -> u32 {
+        self.method2() + 1
+    }
+}
+
+// the stub can be stored in a component like any other value
+blueprint! {
+    struct TestHolder {
+        test: TestTrait,
+    }
+
+    impl TestHolder {
+        pub fn new(test_address: ComponentAddress) -> ComponentAddress {
+            Self {
+                test: test_address.into(),
+            }
+            .instantiate()
+            .globalize()
+        }
+
+        pub fn call_all(&mut self, bucket: Bucket, proof: Proof) -> (Bucket, Option<Bucket>, Proof, u32) {
+            self.test.method1();
+            let (bucket, change) = self.test.method3(bucket);
+            let proof = self.test.method4(proof);
+            (bucket, change, proof, self.test.method5())
+        }
+
+        pub fn address(&self) -> ComponentAddress {
+            self.test.into()
+        }
+    }
+}
@@ -1,9 +1,9 @@
 use scrypto::prelude::*;
 
-use bonding_macros::blueprint_stub; // fancy proc macro that generates a "hidden" empty blueprint so the stubs can be used
+use bonding_macros::blueprint_stub; // fancy proc macro that generates a typed stub for calling another component
 
 // the expected inferface, but for the moment "impl Trait...." cannot be done inside a blueprint
-// use blueprint_stub to generate an empty blueprint so we can get the ABI stubs for use from other blueprints
+// use blueprint_stub to turn the trait into a stub that can be built from the ComponentAddress of any curve component
 #[blueprint_stub]
 pub trait BondingCurve {
     fn get_mint_amount(
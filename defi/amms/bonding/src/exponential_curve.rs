@@ -0,0 +1,145 @@
+use scrypto::prelude::*;
+
+use crate::integral_curve::*;
+use crate::number::*;
+
+struct ExponentialCurve {
+    initial_price: Number,
+    growth_rate: Number,
+    precision_bits: u16,
+}
+
+impl ExponentialCurve {
+    fn new(initial_price: Decimal, growth_rate: Decimal, precision_bits: u16) -> Self {
+        Self {
+            initial_price: number_from_decimal(initial_price, precision_bits),
+            growth_rate: number_from_decimal(growth_rate, precision_bits),
+            precision_bits,
+        }
+    }
+}
+
+impl IntegralCurve for ExponentialCurve {
+    fn precision_bits(&self) -> u16 {
+        self.precision_bits
+    }
+
+    fn price(&self, s: &Number) -> Number {
+        // p(s) = a * e^(b * s)
+        let p = self.precision_bits;
+        mul(
+            &self.initial_price,
+            &exp(&mul(&self.growth_rate, s, p), p),
+            p,
+        )
+    }
+
+    fn reserve(&self, s: &Number) -> Number {
+        // R(s) = a / b * (e^(b * s) - 1)
+        let p = self.precision_bits;
+        let e = exp(&mul(&self.growth_rate, s, p), p) - number_one(p);
+        div(&mul(&self.initial_price, &e, p), &self.growth_rate, p)
+    }
+
+    fn supply(&self, r: &Number) -> Number {
+        // s = ln(1 + b * r / a) / b
+        let p = self.precision_bits;
+        let x = number_one(p) + div(&mul(&self.growth_rate, r, p), &self.initial_price, p);
+        div(&ln(&x, p), &self.growth_rate, p)
+    }
+}
+
+blueprint! {
+    struct ExponentialBondingCurve {
+        initial_price: Decimal,
+        growth_rate: Decimal,
+        precision_bits: u16,
+    }
+
+    impl ExponentialBondingCurve {
+        pub fn new(initial_price: Decimal, growth_rate: Decimal, precision_bits: u16) -> ComponentAddress {
+            debug!(
+                "ExponentialBondingCurve::new called with {} * e^({} * s) @ {} bits",
+                initial_price, growth_rate, precision_bits
+            );
+            assert!(initial_price.is_positive(), "initial price must be positive");
+            assert!(growth_rate.is_positive(), "growth rate must be positive");
+            Self {
+                initial_price,
+                growth_rate,
+                precision_bits,
+            }
+            .instantiate()
+            .globalize()
+        }
+
+        fn curve(&self) -> ExponentialCurve {
+            ExponentialCurve::new(self.initial_price, self.growth_rate, self.precision_bits)
+        }
+
+        pub fn get_initial_supply(&self, collateral_amount: Decimal) -> Decimal {
+            get_initial_supply(&self.curve(), collateral_amount)
+        }
+
+        pub fn get_mint_amount(
+            &self,
+            collateral_amount: Decimal,
+            reserve_amount: Decimal,
+            supply_amount: Decimal,
+        ) -> Decimal {
+            get_mint_amount(&self.curve(), collateral_amount, reserve_amount, supply_amount)
+        }
+
+        pub fn get_return_amount(
+            &self,
+            continuous_amount: Decimal,
+            reserve_amount: Decimal,
+            supply_amount: Decimal,
+        ) -> Decimal {
+            get_return_amount(&self.curve(), continuous_amount, reserve_amount, supply_amount)
+        }
+
+        pub fn get_price(&self, _reserve_amount: Decimal, supply_amount: Decimal) -> Decimal {
+            get_price(&self.curve(), supply_amount)
+        }
+    }
+}
+
+// -------- Testing
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::integral_curve::test::check_round_trip;
+
+    #[test]
+    fn test_1_exponential() {
+        // a = 1, b = ln(2) / 10: the price doubles every 10 tokens
+        let ln_2 = Decimal::from_str("0.069314718055994531").unwrap();
+        let curve = ExponentialCurve::new(1.into(), ln_2, 384);
+        assert_eq!(
+            get_price(&curve, 10.into()),
+            Decimal::from_str("2.000000000000000001").unwrap()
+        );
+        assert_eq!(get_price(&curve, Decimal::zero()), 1.into());
+
+        // R(10) = (2 - 1) / b
+        let reserve =
+            get_initial_supply(&curve, Decimal::from_str("14.426950408889634078").unwrap());
+        assert_eq!(reserve, Decimal::from_str("9.999999999999999999").unwrap());
+    }
+
+    #[test]
+    fn test_2_round_trip() {
+        check_round_trip(&ExponentialCurve::new(
+            1.into(),
+            Decimal::from_str("0.001").unwrap(),
+            384,
+        ));
+        check_round_trip(&ExponentialCurve::new(
+            Decimal::from_str("0.01").unwrap(),
+            Decimal::from_str("0.5").unwrap(),
+            384,
+        ));
+    }
+}
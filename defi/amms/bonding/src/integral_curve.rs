@@ -0,0 +1,160 @@
+use scrypto::prelude::*;
+
+use num_traits::Signed;
+
+use crate::number::*;
+
+// Shared math for curves defined by a price function p(s) of the continuous supply s.
+//
+// The reserve needed to back a supply is the area under the price curve: R(s) = integral of p(s) from 0 to s
+// so buying and selling is just walking up and down the curve:
+//
+// mint amount   = R^-1(R(s) + c) - s
+// return amount = R(s) - R(s - c)
+//
+// Each curve only has to provide p(s), R(s) and the inverse R^-1(r) in closed form.
+pub trait IntegralCurve {
+    fn precision_bits(&self) -> u16;
+
+    // p(s)
+    fn price(&self, s: &Number) -> Number;
+
+    // R(s)
+    fn reserve(&self, s: &Number) -> Number;
+
+    // R^-1(r)
+    fn supply(&self, r: &Number) -> Number;
+}
+
+fn decimal_floor(n: Number, precision_bits: u16) -> Decimal {
+    // always round down so rounding can never let value leak out of the reserve
+    let half_unit = number_from_decimal(
+        Decimal::from_str("0.000000000000000001").unwrap(),
+        precision_bits,
+    ) / Number::from(num_bigint::BigInt::from(2));
+    decimal_from_number(n - half_unit, precision_bits).unwrap()
+}
+
+pub fn get_initial_supply<C: IntegralCurve>(curve: &C, collateral_amount: Decimal) -> Decimal {
+    assert!(!collateral_amount.is_negative());
+
+    if collateral_amount.is_zero() {
+        return Decimal::zero();
+    }
+
+    let precision_bits = curve.precision_bits();
+    let c = number_from_decimal(collateral_amount, precision_bits);
+
+    let result = curve.supply(&c);
+
+    decimal_floor(result, precision_bits)
+}
+
+pub fn get_mint_amount<C: IntegralCurve>(
+    curve: &C,
+    collateral_amount: Decimal,
+    reserve_amount: Decimal,
+    supply_amount: Decimal,
+) -> Decimal {
+    assert!(!collateral_amount.is_negative());
+    assert!(!reserve_amount.is_negative());
+    assert!(!supply_amount.is_negative());
+
+    if collateral_amount.is_zero() {
+        return Decimal::zero();
+    }
+
+    let precision_bits = curve.precision_bits();
+    let c = number_from_decimal(collateral_amount, precision_bits);
+    let s = number_from_decimal(supply_amount, precision_bits);
+
+    let result = curve.supply(&(curve.reserve(&s) + c)) - s;
+
+    assert!(!result.is_negative(), "Calculated negative mint amount");
+
+    decimal_floor(result, precision_bits)
+}
+
+pub fn get_return_amount<C: IntegralCurve>(
+    curve: &C,
+    continuous_amount: Decimal,
+    reserve_amount: Decimal,
+    supply_amount: Decimal,
+) -> Decimal {
+    assert!(!continuous_amount.is_negative());
+    assert!(!reserve_amount.is_negative());
+    assert!(!supply_amount.is_negative());
+    assert!(
+        continuous_amount <= supply_amount,
+        "Cannot return more than the supply"
+    );
+
+    if continuous_amount.is_zero() {
+        return Decimal::zero();
+    }
+
+    let precision_bits = curve.precision_bits();
+    let c = number_from_decimal(continuous_amount, precision_bits);
+    let s = number_from_decimal(supply_amount, precision_bits);
+
+    let result = curve.reserve(&s) - curve.reserve(&(&s - c));
+
+    assert!(!result.is_negative(), "Calculated negative return amount");
+
+    // never return more than what is actually held
+    std::cmp::min(decimal_floor(result, precision_bits), reserve_amount)
+}
+
+pub fn get_price<C: IntegralCurve>(curve: &C, supply_amount: Decimal) -> Decimal {
+    assert!(!supply_amount.is_negative());
+
+    let precision_bits = curve.precision_bits();
+    let s = number_from_decimal(supply_amount, precision_bits);
+
+    let result = curve.price(&s);
+
+    decimal_from_number(result, precision_bits).unwrap()
+}
+
+// -------- Testing
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    // buy in a few steps then sell everything back in a few steps, nothing should be created out of thin air
+    pub fn check_round_trip<C: IntegralCurve>(curve: &C) {
+        let mut reserve_amount = Decimal::zero();
+        let mut supply_amount = get_initial_supply(curve, Decimal::zero());
+        assert_eq!(supply_amount, Decimal::zero());
+
+        let buys: [Decimal; 3] = [100.into(), 2500.into(), Decimal::from_str("0.75").unwrap()];
+        let mut minted = Vec::new();
+        for collateral_amount in buys {
+            let to_mint = get_mint_amount(curve, collateral_amount, reserve_amount, supply_amount);
+            assert!(to_mint > Decimal::zero());
+            reserve_amount += collateral_amount;
+            supply_amount += to_mint;
+            minted.push(to_mint);
+        }
+
+        let paid: Decimal = buys.iter().fold(Decimal::zero(), |a, b| a + *b);
+        let mut returned = Decimal::zero();
+        for continuous_amount in minted.into_iter().rev() {
+            let to_return =
+                get_return_amount(curve, continuous_amount, reserve_amount, supply_amount);
+            reserve_amount -= to_return;
+            supply_amount -= continuous_amount;
+            returned += to_return;
+        }
+
+        assert_eq!(supply_amount, Decimal::zero());
+        assert!(returned <= paid, "value leaked: {} > {}", returned, paid);
+        assert!(
+            paid - returned <= Decimal::from_str("0.000000000000001").unwrap(),
+            "too much lost to rounding: {} vs {}",
+            returned,
+            paid
+        );
+    }
+}
@@ -4,14 +4,36 @@
 //! bonding curve defined by a plugable second component.  It will instantiate a component from the `RatioBondingCurve`
 //! blueprint if no other curve component is provided.
 //!
+//! Optional buy and sell fees (in basis points) can be set when instantiating.  They are collected in a treasury kept
+//! separate from the reserve so the curve math is unaffected, and can be withdrawn with the owner badge returned by `new`.
+//! Buy and sell quotes already have the fees taken out.
+//!
+//! `buy` and `sell` hand the input back instead of failing when the minimum can't be met.  For composing several calls
+//! in one transaction `buy_with_deadline` and `sell_with_deadline` fail the whole transaction instead, also once the
+//! given deadline epoch has passed.
+//!
+//! The owner badge can also move a `BondingAMM` to a new curve component, for example to change the parameters of a
+//! `RatioBondingCurve`.  `propose_curve_migration` starts a timelock (in epochs, set in `new`) after which
+//! `execute_curve_migration` switches curves.  Both check that the new curve gives the same spot price for the current
+//! reserve and supply so existing holders are not repriced.
+//!
 //! There is also an extrememly simple `BasicBondingCurve` blueprint (more like a flat line) which could be used to implement
 //! a simple "wrapped" or "virtual" token from another.  Or it's a a good template for making your own curve component.
 //!
 //! Some useful utilities are included too.  An internal crate is used to provide the `blueprint_stub` macro which
-//! automates creating stub functions from a trait so calling another component is ergonomic.  Also included and
+//! automates creating stub functions from a trait so calling another component is ergonomic.  It works for any component
+//! (methods taking `&self` or `&mut self`, returning buckets, proofs, tuples, options, ...) and is also used by the
+//! no-loss lottery and flash loan examples in this repo.  Also included and
 //! used for the `RatioBondingCurve` is a reusable arbitrary precision number implmentation that converts to/from Decimal
 //! It is precise but not yet optimized.  Bounded (BigInt) or unbounded (BigRational) precision is configurable with a feature flag.
 //!
+//! More curve shapes are available as drop-in curve components, all built on the same number implementation so they work
+//! with either precision feature.  Each is defined by its price function, with buys and sells walking along the area under it:
+//! * `LinearBondingCurve` - price grows by a constant `slope` from an `initial_price`
+//! * `PolynomialBondingCurve` - price is `coefficient * supply ^ degree`
+//! * `ExponentialBondingCurve` - price is `initial_price * e ^ (growth_rate * supply)`
+//! * `SigmoidBondingCurve` - price follows an S-shape, passing `half_max_price` at `inflection_supply` and leveling off at twice that
+//!
 //! # Bonuses:
 //!
 //! * This package is well tested (though not completely) with both unit and integration tests.  Integration
@@ -47,7 +69,12 @@
 mod basic_curve; // a simple flat "curve" 1:1 implementation as a reference
 mod bonding_curve;
 mod default_curve; // a complete non-production bonding curve implementation parametrizable by "curve weight" aka "reserve ratio".  Max precision within Decimal. (ie. precise, but unoptimized)
+mod exponential_curve; // price grows exponentially with supply
+mod integral_curve; // shared buy/sell math for curves defined by a price function and the area under it
+mod linear_curve; // price grows linearly with supply
 mod number; // arbitrary precision math used in default_curve // the trait for cross-blueprint calls for plugable curve math
+mod polynomial_curve; // price is a power of supply
+mod sigmoid_curve; // price follows an S-shape with supply
 
 use scrypto::prelude::*;
 use scrypto_statictypes::prelude::*; // Use https://github.com/devmannic/scrypto_statictypes
@@ -56,12 +83,32 @@ declare_resource!(RESERVE); // resource type used for the reserve pool, collater
 declare_resource!(CONTINUOUS); // resource type minted/burned by the bonding curve
 declare_resource!(AUTH); // resource type for authority (badges) for mint/burn and authentication
 
+const BASIS_POINTS: u16 = 10_000; // fees are given in basis points, 1/100th of a percent
+
+fn fee_amount(amount: Decimal, fee_bps: u16) -> Decimal {
+    amount * fee_bps / BASIS_POINTS
+}
+
+const DEFAULT_MIGRATION_TIMELOCK_EPOCHS: u64 = 100; // used by the convenience constructors
+
+fn is_same_price(a: Decimal, b: Decimal) -> bool {
+    // allow for the last bits of rounding since the curves are free to do their math differently
+    let diff = if a > b { a - b } else { b - a };
+    diff <= a * dec!("0.000000001")
+}
+
 blueprint! {
     struct BondingAMM {
         reserve: VaultOf<RESERVE>,
         continuous: VaultOf<CONTINUOUS>,
         continuous_auth: VaultOf<AUTH>,
         bonding_curve: ComponentAddress, // plugable, does the math
+        treasury: VaultOf<RESERVE>, // collected buy/sell fees, kept separate from the reserve backing the curve
+        owner_badge: ResourceAddress, // can withdraw from the treasury and migrate the curve
+        buy_fee_bps: u16,
+        sell_fee_bps: u16,
+        migration_timelock_epochs: u64,
+        pending_curve: Option<(ComponentAddress, u64)>, // curve to migrate to, and the epoch it can be migrated at
     }
 
     impl BondingAMM {
@@ -70,8 +117,16 @@ blueprint! {
             initial_reserve: BucketOf<RESERVE>,
             continuous_name: String,
             continuous_symbol: String,
-        ) -> (ComponentAddress, BucketOf<CONTINUOUS>) {
-            BondingAMM::new(initial_reserve, continuous_name, continuous_symbol, None)
+        ) -> (ComponentAddress, BucketOf<CONTINUOUS>, Bucket) {
+            BondingAMM::new(
+                initial_reserve,
+                continuous_name,
+                continuous_symbol,
+                None,
+                0,
+                0,
+                DEFAULT_MIGRATION_TIMELOCK_EPOCHS,
+            )
         }
 
         // Convenient Constructor with specified curve - also makes it easy to call from integratino tests -- passing the bonding_curve = None is not possible: FailedToBuildArgs(UnsupportedType(3, Option { value: Custom { name: "scrypto::core::Component", generics: [] } }))
@@ -80,24 +135,55 @@ blueprint! {
             continuous_name: String,
             continuous_symbol: String,
             bonding_curve: ComponentAddress,
-        ) -> (ComponentAddress, BucketOf<CONTINUOUS>) {
+        ) -> (ComponentAddress, BucketOf<CONTINUOUS>, Bucket) {
+            BondingAMM::new(
+                initial_reserve,
+                continuous_name,
+                continuous_symbol,
+                Some(bonding_curve.into()),
+                0,
+                0,
+                DEFAULT_MIGRATION_TIMELOCK_EPOCHS,
+            )
+        }
+
+        // Convenient Constructor with specified curve and fees - also makes it easy to call from integration tests
+        pub fn new_with_fees(
+            initial_reserve: BucketOf<RESERVE>,
+            continuous_name: String,
+            continuous_symbol: String,
+            bonding_curve: ComponentAddress,
+            buy_fee_bps: u16,
+            sell_fee_bps: u16,
+        ) -> (ComponentAddress, BucketOf<CONTINUOUS>, Bucket) {
             BondingAMM::new(
                 initial_reserve,
                 continuous_name,
                 continuous_symbol,
                 Some(bonding_curve.into()),
+                buy_fee_bps,
+                sell_fee_bps,
+                DEFAULT_MIGRATION_TIMELOCK_EPOCHS,
             )
         }
 
         // Main constructor easily called from other blueprints
+        // buy_fee_bps is taken from the collateral on every buy, sell_fee_bps from the reserve returned on every sell
+        // the fees go to a treasury which the returned owner badge can withdraw from
+        // the owner badge can also migrate to a new curve, which takes migration_timelock_epochs to take effect
         pub fn new(
-            initial_reserve: BucketOf<RESERVE>,
+            mut initial_reserve: BucketOf<RESERVE>,
             continuous_name: String,
             continuous_symbol: String,
             bonding_curve: Option<ComponentAddress>,
-        ) -> (ComponentAddress, BucketOf<CONTINUOUS>) {
+            buy_fee_bps: u16,
+            sell_fee_bps: u16,
+            migration_timelock_epochs: u64,
+        ) -> (ComponentAddress, BucketOf<CONTINUOUS>, Bucket) {
             // initial_reserve cannot be empty
             assert!(!initial_reserve.is_empty());
+            assert!(buy_fee_bps < BASIS_POINTS, "buy fee must be less than 100%");
+            assert!(sell_fee_bps < BASIS_POINTS, "sell fee must be less than 100%");
 
             // get the curve Component for the math, or create a default_curve with ratio 1:5 and 384 bit precision (which is plenty for the 1e-18 precision of a Decimal)
             // the 384 bit precision is not needed and ignored when built with feature=use_rationals
@@ -129,22 +215,41 @@ blueprint! {
             let mut continuous = continuous_auth
                 .authorize(|| continuous_def.mint(initial_supply));
 
+            // setup owner badge for the treasury
+            let owner_badge = ResourceBuilder::new_fungible()
+                .divisibility(DIVISIBILITY_NONE)
+                .metadata("name", "BondingAMM owner badge")
+                .initial_supply(1);
+
             // store and instantiate
+            let treasury = VaultOf::with_bucket(initial_reserve.take(0));
             let component = Self {
                 reserve: VaultOf::with_bucket(initial_reserve),
                 continuous: VaultOf::with_bucket(continuous.take(0)),
                 continuous_auth: VaultOf::with_bucket(continuous_auth),
                 bonding_curve,
+                treasury,
+                owner_badge: owner_badge.resource_address(),
+                buy_fee_bps,
+                sell_fee_bps,
+                migration_timelock_epochs,
+                pending_curve: None,
             }
-            .instantiate()
-            .globalize();
+            .instantiate();
+
+            let access_rules = AccessRules::new()
+                .method("withdraw_treasury", rule!(require(owner_badge.resource_address())))
+                .method("propose_curve_migration", rule!(require(owner_badge.resource_address())))
+                .method("cancel_curve_migration", rule!(require(owner_badge.resource_address())))
+                .method("execute_curve_migration", rule!(require(owner_badge.resource_address())))
+                .default(rule!(allow_all));
 
-            (component, continuous)
+            (component.add_access_check(access_rules).globalize(), continuous, owner_badge)
         }
 
         pub fn buy(
             &mut self,
-            collateral: BucketOf<RESERVE>,
+            mut collateral: BucketOf<RESERVE>,
             minimum_to_receive: Decimal,
         ) -> (BucketOf<CONTINUOUS>, BucketOf<RESERVE>) {
             // mint the right amount of CONTINOUS and return it
@@ -159,8 +264,8 @@ blueprint! {
                 return (self.continuous.take(0), collateral);
             }
 
-            // calculate the amount to mint
-            let mint_amount = self.get_buy_quote_amount(collateral.amount());
+            // calculate the amount to mint, after the fee
+            let (mint_amount, fee) = self.get_buy_amounts(collateral.amount());
 
             debug!("will mint CONTINOUS amount: {}", mint_amount);
 
@@ -172,7 +277,8 @@ blueprint! {
                 return (self.continuous.take(0), collateral);
             }
 
-            // keep the collateral
+            // keep the collateral, minus the fee for the treasury
+            self.treasury.put(collateral.take(fee));
             self.reserve.put(collateral);
 
             // mint for return
@@ -205,8 +311,8 @@ blueprint! {
                 debug!("returning CONTINUOUS amount: {}", continuous.amount());
                 return (self.reserve.take(0), continuous);
             }
-            // calculate the amount to return
-            let return_amount = self.get_sell_quote_amount(continuous.amount());
+            // calculate the amount to return, after the fee
+            let (return_amount, fee) = self.get_sell_amounts(continuous.amount());
 
             debug!("will return RESERVE amount: {}", return_amount);
 
@@ -222,10 +328,79 @@ blueprint! {
             self.continuous_auth
                 .authorize(|| continuous.burn());
 
+            // move the fee to the treasury
+            self.treasury.put(self.reserve.take(fee));
+
             // return from reserve vault, and empty CONTINUOUS bucket
             (self.reserve.take(return_amount), self.continuous.take(0))
         }
 
+        // Strict variant of `buy` for composing several calls in one transaction.  Instead of handing back the
+        // collateral it fails the whole transaction if less than minimum_to_receive would be minted, or if the
+        // current epoch is past deadline_epoch
+        pub fn buy_with_deadline(
+            &mut self,
+            collateral: BucketOf<RESERVE>,
+            minimum_to_receive: Decimal,
+            deadline_epoch: u64,
+        ) -> BucketOf<CONTINUOUS> {
+            assert!(!minimum_to_receive.is_negative());
+            let current_epoch = Runtime::current_epoch();
+            assert!(
+                current_epoch <= deadline_epoch,
+                "Deadline passed: current epoch {} is after deadline epoch {}",
+                current_epoch,
+                deadline_epoch
+            );
+            assert!(!collateral.is_empty(), "Cannot buy with empty collateral");
+
+            let mint_amount = self.get_buy_quote_amount(collateral.amount());
+            assert!(
+                !mint_amount.is_zero() && mint_amount >= minimum_to_receive,
+                "Slippage exceeded: would receive {} CONTINUOUS but the minimum is {}",
+                mint_amount,
+                minimum_to_receive
+            );
+
+            let (continuous, collateral) = self.buy(collateral, minimum_to_receive);
+            // buy returns the collateral untouched if the quote and the executed amounts ever disagree, fail rather than keep it
+            assert!(collateral.is_empty(), "Buy did not use all of the collateral");
+            continuous
+        }
+
+        // Strict variant of `sell` for composing several calls in one transaction.  Instead of handing back the
+        // continuous tokens it fails the whole transaction if less than minimum_to_receive would be returned, or if the
+        // current epoch is past deadline_epoch
+        pub fn sell_with_deadline(
+            &mut self,
+            continuous: BucketOf<CONTINUOUS>,
+            minimum_to_receive: Decimal,
+            deadline_epoch: u64,
+        ) -> BucketOf<RESERVE> {
+            assert!(!minimum_to_receive.is_negative());
+            let current_epoch = Runtime::current_epoch();
+            assert!(
+                current_epoch <= deadline_epoch,
+                "Deadline passed: current epoch {} is after deadline epoch {}",
+                current_epoch,
+                deadline_epoch
+            );
+            assert!(!continuous.is_empty(), "Cannot sell empty bucket");
+
+            let return_amount = self.get_sell_quote_amount(continuous.amount());
+            assert!(
+                !return_amount.is_zero() && return_amount >= minimum_to_receive,
+                "Slippage exceeded: would receive {} RESERVE but the minimum is {}",
+                return_amount,
+                minimum_to_receive
+            );
+
+            let (reserve, continuous) = self.sell(continuous, minimum_to_receive);
+            // sell returns the continuous tokens untouched if the quote and the executed amounts ever disagree, fail rather than keep them
+            assert!(continuous.is_empty(), "Sell did not burn all of the continuous tokens");
+            reserve
+        }
+
         pub fn get_price(&self) -> Decimal {
             // use the generated stubs for calling methods on the Component (kind of like a virtual call aka dynamic dispatch, but it happens via the kernel)
             let curve: crate::bonding_curve::BondingCurve = self.bonding_curve.clone().into();
@@ -239,41 +414,132 @@ blueprint! {
             // This variant returns a Proof.  Only possible with sell because
             // for buying, we can't return "proof" because the amount isn't minted yet
 
-            // use the generated stubs for calling methods on the Component (kind of like a virtual call aka dynamic dispatch, but it happens via the kernel)
-            let curve: crate::bonding_curve::BondingCurve = self.bonding_curve.clone().into();
-            // calculate the amount that would be returned
-            let return_amount = curve.get_return_amount(
-                continuous_amount,
-                self.reserve.amount(),
-                self.continuous.resource_manager().total_supply(),
-            );
+            // calculate the amount that would be returned, after the fee
+            let (return_amount, _fee) = self.get_sell_amounts(continuous_amount);
             // return a placeholder for the amount as proof we have it in reserve
             let bucket = self.reserve.take(return_amount);
             bucket.create_proof() // return proof of amount, but don't give it away
         }
 
+        // the amount minted for the collateral_amount, after the buy fee is taken
         pub fn get_buy_quote_amount(&self, collateral_amount: Decimal) -> Decimal {
+            let (mint_amount, _fee) = self.get_buy_amounts(collateral_amount);
+            mint_amount
+        }
+
+        // the amount returned for the continuous_amount, after the sell fee is taken
+        pub fn get_sell_quote_amount(&self, continuous_amount: Decimal) -> Decimal {
+            // interestingly, trying to reuse self.get_sell_quote to get a BucketRef and then look at the amount leads to failure with dangling buckets no matter what I've tried
+            // instead just calculate the amount directly
+            let (return_amount, _fee) = self.get_sell_amounts(continuous_amount);
+            return_amount
+        }
+
+        pub fn get_fees(&self) -> (u16, u16) {
+            (self.buy_fee_bps, self.sell_fee_bps)
+        }
+
+        pub fn get_treasury_amount(&self) -> Decimal {
+            self.treasury.amount()
+        }
+
+        // requires the owner badge
+        pub fn withdraw_treasury(&mut self, amount: Decimal) -> BucketOf<RESERVE> {
+            assert!(!amount.is_negative());
+            assert!(
+                amount <= self.treasury.amount(),
+                "Cannot withdraw more than the treasury holds"
+            );
+            self.treasury.take(amount)
+        }
+
+        pub fn get_bonding_curve(&self) -> ComponentAddress {
+            self.bonding_curve
+        }
+
+        // the curve that will be migrated to, and the epoch from which the migration can be executed
+        pub fn get_pending_curve_migration(&self) -> Option<(ComponentAddress, u64)> {
+            self.pending_curve
+        }
+
+        // requires the owner badge
+        // starts the timelock for migrating to new_curve, replacing any migration that is already pending
+        // new_curve must give the same spot price as the current curve so holders aren't repriced
+        pub fn propose_curve_migration(&mut self, new_curve: ComponentAddress) {
+            assert!(new_curve != self.bonding_curve, "The new curve must differ from the current curve");
+            self.assert_same_price(new_curve);
+
+            let migration_epoch = Runtime::current_epoch() + self.migration_timelock_epochs;
+            debug!("curve migration to {} possible from epoch {}", new_curve, migration_epoch);
+            self.pending_curve = Some((new_curve, migration_epoch));
+        }
+
+        // requires the owner badge
+        pub fn cancel_curve_migration(&mut self) {
+            assert!(self.pending_curve.is_some(), "No curve migration pending");
+            self.pending_curve = None;
+        }
+
+        // requires the owner badge
+        // switches to the pending curve once the timelock has passed
+        pub fn execute_curve_migration(&mut self) {
+            let (new_curve, migration_epoch) = self.pending_curve.expect("No curve migration pending");
+            let current_epoch = Runtime::current_epoch();
+            assert!(
+                current_epoch >= migration_epoch,
+                "Curve migration is timelocked until epoch {}, current epoch is {}",
+                migration_epoch,
+                current_epoch
+            );
+            // reserve and supply may have moved since the proposal
+            self.assert_same_price(new_curve);
+
+            self.bonding_curve = new_curve;
+            self.pending_curve = None;
+        }
+
+        fn assert_same_price(&self, new_curve: ComponentAddress) {
+            // use the generated stubs for calling methods on the Component (kind of like a virtual call aka dynamic dispatch, but it happens via the kernel)
+            let curve: crate::bonding_curve::BondingCurve = new_curve.into();
+            let new_price = curve.get_price(
+                self.reserve.amount(),
+                self.continuous.resource_manager().total_supply(),
+            );
+            let price = self.get_price();
+            assert!(
+                is_same_price(price, new_price),
+                "The new curve must have the same spot price: current price {}, new curve price {}",
+                price,
+                new_price
+            );
+        }
+
+        // (amount to mint, fee) for the collateral_amount
+        fn get_buy_amounts(&self, collateral_amount: Decimal) -> (Decimal, Decimal) {
+            let fee = fee_amount(collateral_amount, self.buy_fee_bps);
             // use the generated stubs for calling methods on the Component (kind of like a virtual call aka dynamic dispatch, but it happens via the kernel)
             let curve: crate::bonding_curve::BondingCurve = self.bonding_curve.clone().into();
             // calculate amount that would be minted
-            curve.get_mint_amount(
-                collateral_amount,
+            let mint_amount = curve.get_mint_amount(
+                collateral_amount - fee,
                 self.reserve.amount(),
                 self.continuous.resource_manager().total_supply(),
-            )
+            );
+            (mint_amount, fee)
         }
 
-        pub fn get_sell_quote_amount(&self, continuous_amount: Decimal) -> Decimal {
-            // interestingly, trying to reuse self.get_sell_quote to get a BucketRef and then look at the amount leads to failure with dangling buckets no matter what I've tried
-            // instead just calculate the amount directly
+        // (amount to return, fee) for the continuous_amount
+        fn get_sell_amounts(&self, continuous_amount: Decimal) -> (Decimal, Decimal) {
             // use the generated stubs for calling methods on the Component (kind of like a virtual call aka dynamic dispatch, but it happens via the kernel)
             let curve: crate::bonding_curve::BondingCurve = self.bonding_curve.clone().into();
-            // calculate the amount that would be returned
-            curve.get_return_amount(
+            // calculate the amount that would be released from the reserve
+            let reserve_amount = curve.get_return_amount(
                 continuous_amount,
                 self.reserve.amount(),
                 self.continuous.resource_manager().total_supply(),
-            )
+            );
+            let fee = fee_amount(reserve_amount, self.sell_fee_bps);
+            (reserve_amount - fee, fee)
         }
     }
 }
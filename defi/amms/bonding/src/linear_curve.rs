@@ -0,0 +1,153 @@
+use scrypto::prelude::*;
+
+use num_traits::Zero;
+
+use crate::integral_curve::*;
+use crate::number::*;
+
+struct LinearCurve {
+    slope: Number,
+    initial_price: Number,
+    precision_bits: u16,
+}
+
+impl LinearCurve {
+    fn new(slope: Decimal, initial_price: Decimal, precision_bits: u16) -> Self {
+        Self {
+            slope: number_from_decimal(slope, precision_bits),
+            initial_price: number_from_decimal(initial_price, precision_bits),
+            precision_bits,
+        }
+    }
+}
+
+impl IntegralCurve for LinearCurve {
+    fn precision_bits(&self) -> u16 {
+        self.precision_bits
+    }
+
+    fn price(&self, s: &Number) -> Number {
+        // p(s) = m * s + b
+        mul(&self.slope, s, self.precision_bits) + &self.initial_price
+    }
+
+    fn reserve(&self, s: &Number) -> Number {
+        // R(s) = m * s^2 / 2 + b * s
+        let p = self.precision_bits;
+        let two = number_from_u32(2, p);
+        div(&mul(&self.slope, &mul(s, s, p), p), &two, p) + mul(&self.initial_price, s, p)
+    }
+
+    fn supply(&self, r: &Number) -> Number {
+        let p = self.precision_bits;
+        if self.slope.is_zero() {
+            // flat: s = r / b
+            return div(r, &self.initial_price, p);
+        }
+        // s = (sqrt(b^2 + 2 * m * r) - b) / m
+        let two = number_from_u32(2, p);
+        let b2 = mul(&self.initial_price, &self.initial_price, p);
+        let root = sqrt(&(b2 + mul(&two, &mul(&self.slope, r, p), p)), p);
+        div(&(root - &self.initial_price), &self.slope, p)
+    }
+}
+
+blueprint! {
+    struct LinearBondingCurve {
+        slope: Decimal,
+        initial_price: Decimal,
+        precision_bits: u16,
+    }
+
+    impl LinearBondingCurve {
+        pub fn new(slope: Decimal, initial_price: Decimal, precision_bits: u16) -> ComponentAddress {
+            debug!(
+                "LinearBondingCurve::new called with slope {} initial_price {} @ {} bits",
+                slope, initial_price, precision_bits
+            );
+            assert!(!slope.is_negative(), "slope cannot be negative");
+            assert!(!initial_price.is_negative(), "initial price cannot be negative");
+            assert!(
+                !(slope.is_zero() && initial_price.is_zero()),
+                "slope and initial price cannot both be zero"
+            );
+            Self {
+                slope,
+                initial_price,
+                precision_bits,
+            }
+            .instantiate()
+            .globalize()
+        }
+
+        fn curve(&self) -> LinearCurve {
+            LinearCurve::new(self.slope, self.initial_price, self.precision_bits)
+        }
+
+        pub fn get_initial_supply(&self, collateral_amount: Decimal) -> Decimal {
+            get_initial_supply(&self.curve(), collateral_amount)
+        }
+
+        pub fn get_mint_amount(
+            &self,
+            collateral_amount: Decimal,
+            reserve_amount: Decimal,
+            supply_amount: Decimal,
+        ) -> Decimal {
+            get_mint_amount(&self.curve(), collateral_amount, reserve_amount, supply_amount)
+        }
+
+        pub fn get_return_amount(
+            &self,
+            continuous_amount: Decimal,
+            reserve_amount: Decimal,
+            supply_amount: Decimal,
+        ) -> Decimal {
+            get_return_amount(&self.curve(), continuous_amount, reserve_amount, supply_amount)
+        }
+
+        pub fn get_price(&self, _reserve_amount: Decimal, supply_amount: Decimal) -> Decimal {
+            get_price(&self.curve(), supply_amount)
+        }
+    }
+}
+
+// -------- Testing
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::integral_curve::test::check_round_trip;
+
+    #[test]
+    fn test_1_linear_reserve() {
+        // m = 0.5, b = 1: reserve for 10 tokens is 0.25 * 100 + 10 = 35
+        let curve = LinearCurve::new(Decimal::from_str("0.5").unwrap(), 1.into(), 384);
+        assert_eq!(get_initial_supply(&curve, 35.into()), 10.into());
+        assert_eq!(get_price(&curve, 10.into()), 6.into());
+        assert_eq!(
+            get_mint_amount(&curve, 85.into(), 35.into(), 10.into()),
+            10.into()
+        );
+        assert_eq!(
+            get_return_amount(&curve, 10.into(), 120.into(), 20.into()),
+            85.into()
+        );
+    }
+
+    #[test]
+    fn test_2_flat() {
+        let curve = LinearCurve::new(Decimal::zero(), 2.into(), 384);
+        assert_eq!(get_initial_supply(&curve, 10.into()), 5.into());
+        assert_eq!(get_price(&curve, 10.into()), 2.into());
+    }
+
+    #[test]
+    fn test_3_round_trip() {
+        check_round_trip(&LinearCurve::new(
+            Decimal::from_str("0.001").unwrap(),
+            1.into(),
+            384,
+        ));
+    }
+}
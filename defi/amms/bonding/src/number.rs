@@ -30,6 +30,18 @@ mod details {
     pub fn pow_nd(base: &Number, n: u32, d: u32) -> Number {
         base.pow(n).nth_root(d)
     }
+
+    #[inline(always)]
+    pub fn nth_root(a: &Number, n: u32, one: &Number) -> Number {
+        // scale up first so the integer root keeps the full fixed point precision
+        (a * one.pow(n - 1)).nth_root(n)
+    }
+
+    #[inline(always)]
+    pub fn round(b: Number) -> Number {
+        // fixed point numbers never grow beyond their precision
+        b
+    }
 }
 
 #[cfg(feature = "use_rationals")]
@@ -65,6 +77,19 @@ mod details {
         let denom_root = denom.nth_root(d);
         BigRational::new(numer_root, denom_root)
     }
+
+    #[inline(always)]
+    pub fn nth_root(a: &Number, n: u32, _one: &Number) -> Number {
+        pow_nd(a, 1, n)
+    }
+
+    pub fn round(b: Number) -> Number {
+        // keep numerators and denominators from growing without bounds in iterative calculations
+        // 1e-54 is plenty for the 1e-18 precision of a Decimal
+        let multiple: num_bigint::BigInt = 1_000_000_000_000_000_000u128.into();
+        let multiple = multiple.pow(3);
+        BigRational::new((b * &multiple).round().numer().clone(), multiple)
+    }
 }
 
 pub use details::Number;
@@ -112,6 +137,139 @@ pub fn scaled_power(
     scale * n / d
 }
 
+pub fn number_from_u32(i: u32, precision_bits: u16) -> Number {
+    number_from_decimal(Decimal::from(i), precision_bits)
+}
+
+pub fn number_one(precision_bits: u16) -> Number {
+    number_from_u32(1, precision_bits)
+}
+
+// multiplication and division that work for both the fixed point (BigInt) and the BigRational representation
+
+pub fn mul(a: &Number, b: &Number, precision_bits: u16) -> Number {
+    details::round(a * b / number_one(precision_bits))
+}
+
+pub fn div(a: &Number, b: &Number, precision_bits: u16) -> Number {
+    assert!(!b.is_zero(), "div divide by zero"); // nicer error message
+    details::round(a * number_one(precision_bits) / b)
+}
+
+pub fn pow(a: &Number, n: u32, precision_bits: u16) -> Number {
+    // scaled_power used here for arbitrary precision way to do: a^n
+    let one = number_one(precision_bits);
+    scaled_power(&one, a, &one, n, 1)
+}
+
+pub fn nth_root(a: &Number, n: u32, precision_bits: u16) -> Number {
+    assert!(!a.is_negative(), "nth_root of negative number");
+    assert!(n != 0, "nth_root with zero degree");
+    details::nth_root(a, n, &number_one(precision_bits))
+}
+
+pub fn sqrt(a: &Number, precision_bits: u16) -> Number {
+    nth_root(a, 2, precision_bits)
+}
+
+fn atanh_series(z: &Number, precision_bits: u16) -> Number {
+    // atanh(z) = z + z^3/3 + z^5/5 + ... converges quickly for |z| <= 1/3
+    let z2 = mul(z, z, precision_bits);
+    let mut term = z.clone();
+    let mut sum = Number::zero();
+    let mut i = 1;
+    while !term.is_zero() {
+        sum = sum + div(&term, &number_from_u32(i, precision_bits), precision_bits);
+        term = mul(&term, &z2, precision_bits);
+        i += 2;
+    }
+    sum
+}
+
+fn ln_2(precision_bits: u16) -> Number {
+    // ln(2) = 2 * atanh(1/3)
+    let third = div(
+        &number_one(precision_bits),
+        &number_from_u32(3, precision_bits),
+        precision_bits,
+    );
+    let half_ln_2 = atanh_series(&third, precision_bits);
+    &half_ln_2 + &half_ln_2
+}
+
+pub fn ln(a: &Number, precision_bits: u16) -> Number {
+    assert!(*a > Number::zero(), "ln of non-positive number");
+    let one = number_one(precision_bits);
+    let two = number_from_u32(2, precision_bits);
+
+    // reduce to a = m * 2^k with 1 <= m < 2
+    let mut m = a.clone();
+    let mut k: i32 = 0;
+    while m >= two {
+        m = div(&m, &two, precision_bits);
+        k += 1;
+    }
+    while m < one {
+        m = &m + &m;
+        k -= 1;
+    }
+
+    // ln(m) = 2 * atanh((m - 1) / (m + 1))
+    let z = div(&(&m - &one), &(&m + &one), precision_bits);
+    let half_ln_m = atanh_series(&z, precision_bits);
+    let ln_m = &half_ln_m + &half_ln_m;
+
+    // ln(a) = ln(m) + k * ln(2)
+    let ln_2 = ln_2(precision_bits);
+    let mut result = ln_m;
+    for _ in 0..k.abs() {
+        if k > 0 {
+            result = result + &ln_2;
+        } else {
+            result = result - &ln_2;
+        }
+    }
+    result
+}
+
+pub fn exp(a: &Number, precision_bits: u16) -> Number {
+    assert!(*a >= Number::zero(), "exp of negative number");
+    // e^128 is way beyond the range of a Decimal
+    assert!(
+        *a <= number_from_u32(128, precision_bits),
+        "exp argument too large"
+    );
+
+    // reduce to a = k * ln(2) + r with 0 <= r < ln(2)
+    let ln_2 = ln_2(precision_bits);
+    let mut r = a.clone();
+    let mut k = 0;
+    while r >= ln_2 {
+        r = r - &ln_2;
+        k += 1;
+    }
+
+    // e^r = 1 + r + r^2/2! + r^3/3! + ...
+    let mut term = number_one(precision_bits);
+    let mut sum = Number::zero();
+    let mut i = 1;
+    while !term.is_zero() {
+        sum = sum + &term;
+        term = div(
+            &mul(&term, &r, precision_bits),
+            &number_from_u32(i, precision_bits),
+            precision_bits,
+        );
+        i += 1;
+    }
+
+    // e^a = e^r * 2^k
+    for _ in 0..k {
+        sum = &sum + &sum;
+    }
+    sum
+}
+
 #[cfg(test)]
 mod test {
     use super::details::bigint_from_number;
@@ -146,4 +304,38 @@ mod test {
         let b = bigint_from_number(b, precision_bits);
         assert_eq!(b.to_i128().unwrap(), i);
     }
+
+    #[test]
+    fn test_sqrt() {
+        let precision_bits = 384;
+        let a = number_from_decimal(dec!("2.25"), precision_bits);
+        let result = decimal_from_number(sqrt(&a, precision_bits), precision_bits);
+        assert_eq!(result, Some(dec!("1.5")));
+    }
+
+    #[test]
+    fn test_exp_ln() {
+        let precision_bits = 384;
+        let one = number_one(precision_bits);
+        let e = decimal_from_number(exp(&one, precision_bits), precision_bits);
+        assert_eq!(e, Some(dec!("2.718281828459045235")));
+
+        let a = number_from_decimal(dec!("10"), precision_bits);
+        let ln_10 = decimal_from_number(ln(&a, precision_bits), precision_bits);
+        assert_eq!(ln_10, Some(dec!("2.302585092994045684")));
+
+        // ln(0.5) + ln(8) = ln(4)
+        let a = number_from_decimal(dec!("0.5"), precision_bits);
+        let b = number_from_decimal(dec!("8"), precision_bits);
+        let ln_4 = decimal_from_number(
+            ln(&a, precision_bits) + ln(&b, precision_bits),
+            precision_bits,
+        );
+        assert_eq!(ln_4, Some(dec!("1.386294361119890619")));
+
+        let a = number_from_decimal(dec!("3.7"), precision_bits);
+        let round_trip =
+            decimal_from_number(exp(&ln(&a, precision_bits), precision_bits), precision_bits);
+        assert_eq!(round_trip, Some(dec!("3.7")));
+    }
 }
@@ -0,0 +1,148 @@
+use scrypto::prelude::*;
+
+use crate::integral_curve::*;
+use crate::number::*;
+
+struct PolynomialCurve {
+    coefficient: Number,
+    degree: u32,
+    precision_bits: u16,
+}
+
+impl PolynomialCurve {
+    fn new(coefficient: Decimal, degree: u32, precision_bits: u16) -> Self {
+        Self {
+            coefficient: number_from_decimal(coefficient, precision_bits),
+            degree,
+            precision_bits,
+        }
+    }
+}
+
+impl IntegralCurve for PolynomialCurve {
+    fn precision_bits(&self) -> u16 {
+        self.precision_bits
+    }
+
+    fn price(&self, s: &Number) -> Number {
+        // p(s) = k * s^n
+        let p = self.precision_bits;
+        mul(&self.coefficient, &pow(s, self.degree, p), p)
+    }
+
+    fn reserve(&self, s: &Number) -> Number {
+        // R(s) = k * s^(n+1) / (n+1)
+        let p = self.precision_bits;
+        let n1 = number_from_u32(self.degree + 1, p);
+        div(
+            &mul(&self.coefficient, &pow(s, self.degree + 1, p), p),
+            &n1,
+            p,
+        )
+    }
+
+    fn supply(&self, r: &Number) -> Number {
+        // s = ((n+1) * r / k)^(1/(n+1))
+        let p = self.precision_bits;
+        let n1 = number_from_u32(self.degree + 1, p);
+        nth_root(
+            &div(&mul(&n1, r, p), &self.coefficient, p),
+            self.degree + 1,
+            p,
+        )
+    }
+}
+
+blueprint! {
+    struct PolynomialBondingCurve {
+        coefficient: Decimal,
+        degree: u32,
+        precision_bits: u16,
+    }
+
+    impl PolynomialBondingCurve {
+        pub fn new(coefficient: Decimal, degree: u32, precision_bits: u16) -> ComponentAddress {
+            debug!(
+                "PolynomialBondingCurve::new called with {} * s^{} @ {} bits",
+                coefficient, degree, precision_bits
+            );
+            assert!(coefficient.is_positive(), "coefficient must be positive");
+            Self {
+                coefficient,
+                degree,
+                precision_bits,
+            }
+            .instantiate()
+            .globalize()
+        }
+
+        fn curve(&self) -> PolynomialCurve {
+            PolynomialCurve::new(self.coefficient, self.degree, self.precision_bits)
+        }
+
+        pub fn get_initial_supply(&self, collateral_amount: Decimal) -> Decimal {
+            get_initial_supply(&self.curve(), collateral_amount)
+        }
+
+        pub fn get_mint_amount(
+            &self,
+            collateral_amount: Decimal,
+            reserve_amount: Decimal,
+            supply_amount: Decimal,
+        ) -> Decimal {
+            get_mint_amount(&self.curve(), collateral_amount, reserve_amount, supply_amount)
+        }
+
+        pub fn get_return_amount(
+            &self,
+            continuous_amount: Decimal,
+            reserve_amount: Decimal,
+            supply_amount: Decimal,
+        ) -> Decimal {
+            get_return_amount(&self.curve(), continuous_amount, reserve_amount, supply_amount)
+        }
+
+        pub fn get_price(&self, _reserve_amount: Decimal, supply_amount: Decimal) -> Decimal {
+            get_price(&self.curve(), supply_amount)
+        }
+    }
+}
+
+// -------- Testing
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::integral_curve::test::check_round_trip;
+
+    #[test]
+    fn test_1_quadratic() {
+        // k = 3, n = 2: R(s) = s^3 so 1000 reserve backs 10 tokens
+        let curve = PolynomialCurve::new(3.into(), 2, 384);
+        assert_eq!(get_initial_supply(&curve, 1000.into()), 10.into());
+        assert_eq!(get_price(&curve, 10.into()), 300.into());
+        assert_eq!(
+            get_mint_amount(&curve, 7000.into(), 1000.into(), 10.into()),
+            10.into()
+        );
+        assert_eq!(
+            get_return_amount(&curve, 10.into(), 8000.into(), 20.into()),
+            7000.into()
+        );
+    }
+
+    #[test]
+    fn test_2_round_trip() {
+        check_round_trip(&PolynomialCurve::new(
+            Decimal::from_str("0.0001").unwrap(),
+            2,
+            384,
+        ));
+        check_round_trip(&PolynomialCurve::new(
+            Decimal::from_str("0.5").unwrap(),
+            1,
+            384,
+        ));
+        check_round_trip(&PolynomialCurve::new(2.into(), 0, 384));
+    }
+}
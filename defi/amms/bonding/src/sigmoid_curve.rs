@@ -0,0 +1,177 @@
+use scrypto::prelude::*;
+
+use crate::integral_curve::*;
+use crate::number::*;
+
+// an algebraic sigmoid, which unlike the logistic function has a closed form for the integral and its inverse
+struct SigmoidCurve {
+    half_max_price: Number,
+    inflection_supply: Number,
+    steepness: Number,
+    precision_bits: u16,
+}
+
+impl SigmoidCurve {
+    fn new(
+        half_max_price: Decimal,
+        inflection_supply: Decimal,
+        steepness: Decimal,
+        precision_bits: u16,
+    ) -> Self {
+        Self {
+            half_max_price: number_from_decimal(half_max_price, precision_bits),
+            inflection_supply: number_from_decimal(inflection_supply, precision_bits),
+            steepness: number_from_decimal(steepness, precision_bits),
+            precision_bits,
+        }
+    }
+
+    // sqrt(c + x^2)
+    fn hypot(&self, x: &Number) -> Number {
+        let p = self.precision_bits;
+        sqrt(&(&self.steepness + mul(x, x, p)), p)
+    }
+}
+
+impl IntegralCurve for SigmoidCurve {
+    fn precision_bits(&self) -> u16 {
+        self.precision_bits
+    }
+
+    fn price(&self, s: &Number) -> Number {
+        // p(s) = a * ((s - b) / sqrt(c + (s - b)^2) + 1)
+        let p = self.precision_bits;
+        let x = s - &self.inflection_supply;
+        let y = div(&x, &self.hypot(&x), p) + number_one(p);
+        mul(&self.half_max_price, &y, p)
+    }
+
+    fn reserve(&self, s: &Number) -> Number {
+        // R(s) = a * (sqrt(c + (s - b)^2) - sqrt(c + b^2) + s)
+        let p = self.precision_bits;
+        let x = s - &self.inflection_supply;
+        let y = self.hypot(&x) - self.hypot(&self.inflection_supply) + s;
+        mul(&self.half_max_price, &y, p)
+    }
+
+    fn supply(&self, r: &Number) -> Number {
+        // with L = R / a + sqrt(c + b^2) - b solving sqrt(c + u^2) + u = L for u = s - b gives:
+        // s = b + (L^2 - c) / (2 * L)
+        let p = self.precision_bits;
+        let l = div(r, &self.half_max_price, p) + self.hypot(&self.inflection_supply)
+            - &self.inflection_supply;
+        let u = div(&(mul(&l, &l, p) - &self.steepness), &(&l + &l), p);
+        &self.inflection_supply + u
+    }
+}
+
+blueprint! {
+    struct SigmoidBondingCurve {
+        half_max_price: Decimal,
+        inflection_supply: Decimal,
+        steepness: Decimal,
+        precision_bits: u16,
+    }
+
+    impl SigmoidBondingCurve {
+        // the price starts near zero, passes half_max_price at inflection_supply and levels off at 2 * half_max_price
+        // the bigger steepness is, the flatter the curve is around the inflection point
+        pub fn new(
+            half_max_price: Decimal,
+            inflection_supply: Decimal,
+            steepness: Decimal,
+            precision_bits: u16,
+        ) -> ComponentAddress {
+            debug!(
+                "SigmoidBondingCurve::new called with half max price {} at supply {} steepness {} @ {} bits",
+                half_max_price, inflection_supply, steepness, precision_bits
+            );
+            assert!(half_max_price.is_positive(), "half max price must be positive");
+            assert!(!inflection_supply.is_negative(), "inflection supply cannot be negative");
+            assert!(steepness.is_positive(), "steepness must be positive");
+            Self {
+                half_max_price,
+                inflection_supply,
+                steepness,
+                precision_bits,
+            }
+            .instantiate()
+            .globalize()
+        }
+
+        fn curve(&self) -> SigmoidCurve {
+            SigmoidCurve::new(
+                self.half_max_price,
+                self.inflection_supply,
+                self.steepness,
+                self.precision_bits,
+            )
+        }
+
+        pub fn get_initial_supply(&self, collateral_amount: Decimal) -> Decimal {
+            get_initial_supply(&self.curve(), collateral_amount)
+        }
+
+        pub fn get_mint_amount(
+            &self,
+            collateral_amount: Decimal,
+            reserve_amount: Decimal,
+            supply_amount: Decimal,
+        ) -> Decimal {
+            get_mint_amount(&self.curve(), collateral_amount, reserve_amount, supply_amount)
+        }
+
+        pub fn get_return_amount(
+            &self,
+            continuous_amount: Decimal,
+            reserve_amount: Decimal,
+            supply_amount: Decimal,
+        ) -> Decimal {
+            get_return_amount(&self.curve(), continuous_amount, reserve_amount, supply_amount)
+        }
+
+        pub fn get_price(&self, _reserve_amount: Decimal, supply_amount: Decimal) -> Decimal {
+            get_price(&self.curve(), supply_amount)
+        }
+    }
+}
+
+// -------- Testing
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::integral_curve::test::check_round_trip;
+
+    #[test]
+    fn test_1_sigmoid() {
+        // a = 5, b = 1000, c = 10000
+        let curve = SigmoidCurve::new(5.into(), 1000.into(), 10000.into(), 384);
+        assert_eq!(get_price(&curve, 1000.into()), 5.into());
+        // (1100 - 1000) / sqrt(10000 + 100^2) = 1 / sqrt(2)
+        assert_eq!(
+            get_price(&curve, 1100.into()),
+            Decimal::from_str("8.535533905932737622").unwrap()
+        );
+        assert!(get_price(&curve, Decimal::zero()) < Decimal::from_str("0.03").unwrap());
+        assert!(get_price(&curve, 100000.into()) < 10.into());
+
+        // R(1000) = 5 * (100 - sqrt(1010000) + 1000)
+        let reserve = Decimal::from_str("475.062189439554864890").unwrap();
+        assert_eq!(
+            get_initial_supply(&curve, reserve),
+            Decimal::from_str("999.999999999999999999").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_2_round_trip() {
+        check_round_trip(&SigmoidCurve::new(5.into(), 1000.into(), 10000.into(), 384));
+        check_round_trip(&SigmoidCurve::new(
+            Decimal::from_str("0.5").unwrap(),
+            10.into(),
+            1.into(),
+            384,
+        ));
+    }
+}
@@ -1,5 +1,7 @@
 use radix_engine::ledger::*;
+use radix_engine::model::Receipt;
 use radix_engine::transaction::*;
+use scrypto::crypto::{EcdsaPrivateKey, EcdsaPublicKey};
 use scrypto::prelude::*;
 use scrypto_unit::*;
 
@@ -86,7 +88,7 @@ fn test_1() {
     // since it is not the returned Component but just the one that is created first.
 
     // instead do this
-    let ret: (ComponentAddress, Bucket) = return_of_call_function(&mut receipt, BLUEPRINT);
+    let ret: (ComponentAddress, Bucket, Bucket) = return_of_call_function(&mut receipt, BLUEPRINT);
     let amm = ret.0;
 
     // now switch to an investor
@@ -212,7 +214,7 @@ fn test_2_basic_curve() {
     // since it is not the returned Component but just the one that is created first.
 
     // do this instead
-    let ret: (ComponentAddress, Bucket) = return_of_call_function(&mut receipt, BLUEPRINT);
+    let ret: (ComponentAddress, Bucket, Bucket) = return_of_call_function(&mut receipt, BLUEPRINT);
     let amm = ret.0;
 
     // now switch to an investor
@@ -317,7 +319,7 @@ fn test_3_quotes() {
     let _control_addr = receipt.new_resource_addresses[0]; // this should be the CONTROL
     let _continuous_addr = receipt.new_resource_addresses[1]; // this should be the CONTINUOUS
 
-    let ret: (ComponentAddress, Bucket) = return_of_call_function(&mut receipt, BLUEPRINT);
+    let ret: (ComponentAddress, Bucket, Bucket) = return_of_call_function(&mut receipt, BLUEPRINT);
     let amm = ret.0;
 
     // now switch to an investor
@@ -362,3 +364,567 @@ fn test_3_quotes() {
     let reserve_in_account = env.get_amount_for_rd(user.account, reserve_def);
     assert_eq!(reserve_in_account, expected_reserve_in_account);
 }
+
+// buy with a freshly created curve component and sell everything back, the investor should never end up with more than they started with
+fn round_trip_with_curve(curve_blueprint: &str, curve_args: Vec<Vec<u8>>) {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut env = TestEnv::new(&mut ledger);
+    let (_owner, investor, reserve_def) = setup_fixture(&mut env);
+
+    const BLUEPRINT: &str = "BondingAMM";
+
+    // switch to the owner to instantiate a new BondingAMM
+    env.acting_as("owner");
+
+    // create the curve
+    let mut receipt = env.call_function(curve_blueprint, "new", curve_args);
+    println!("{}::new: receipt: {:?}", curve_blueprint, receipt);
+    assert!(receipt.result.is_ok());
+    let curve: ComponentAddress = return_of_call_function(&mut receipt, curve_blueprint);
+
+    // instantiate amm with the curve
+    let mut receipt = env.call_function_aux(
+        BLUEPRINT,
+        "new_with_curve",
+        vec![
+            get_param_bucket!(dec!(60000u64), reserve_def, env.current_user.unwrap().account),
+            get_param_value!("Continuous"),
+            get_param_value!("XC"),
+            get_param_value!(curve),
+        ],
+    );
+    println!("new_with_curve: receipt: {:?}", receipt);
+    assert!(receipt.result.is_ok());
+
+    // this is brittle checking the defs based on order...
+    let continuous_addr = receipt.new_resource_addresses[1]; // this should be the CONTINUOUS
+
+    let ret: (ComponentAddress, Bucket, Bucket) = return_of_call_function(&mut receipt, BLUEPRINT);
+    let amm = ret.0;
+
+    // now switch to an investor
+    env.acting_as("investor");
+    let user = investor;
+
+    let receipt = env.call_method_aux(
+        amm,
+        "buy",
+        vec![
+            get_param_bucket!(dec!(300u64), reserve_def, env.current_user.unwrap().account),
+            get_param_value!(dec!(0u32)),
+        ]
+    );
+    println!("buy: receipt: {:?}", receipt);
+    assert!(receipt.result.is_ok());
+
+    let continuous_in_account = env.get_amount_for_rd(user.account, continuous_addr);
+    println!("bought continuous: {}", continuous_in_account);
+    assert!(continuous_in_account > Decimal::zero());
+
+    // sell all of it back
+    let receipt = env.call_method_aux(
+        amm,
+        "sell",
+        vec![
+            get_param_bucket!(continuous_in_account, continuous_addr, env.current_user.unwrap().account),
+            get_param_value!(dec!(0u32)),
+        ],
+    );
+    println!("sell: receipt: {:?}", receipt);
+    assert!(receipt.result.is_ok());
+
+    // no value leaked out of the reserve, and no more than rounding dust was lost
+    let reserve_in_account = env.get_amount_for_rd(user.account, reserve_def);
+    println!("actual   reserve after sell: {}", reserve_in_account);
+    assert!(reserve_in_account <= dec!(1_000_000u64));
+    assert!(reserve_in_account >= dec!(1_000_000u64) - dec!("0.000000000001"));
+    let continuous_in_account = env.get_amount_for_rd(user.account, continuous_addr);
+    assert_eq!(continuous_in_account, Decimal::zero());
+}
+
+#[test]
+fn test_4_linear_curve() {
+    round_trip_with_curve(
+        "LinearBondingCurve",
+        vec![
+            scrypto_encode(&dec!("0.001")), // slope
+            scrypto_encode(&dec!("1")),     // initial_price
+            scrypto_encode(&384u16),
+        ],
+    );
+}
+
+#[test]
+fn test_5_polynomial_curve() {
+    round_trip_with_curve(
+        "PolynomialBondingCurve",
+        vec![
+            scrypto_encode(&dec!("0.0001")), // coefficient
+            scrypto_encode(&2u32),           // degree
+            scrypto_encode(&384u16),
+        ],
+    );
+}
+
+#[test]
+fn test_6_exponential_curve() {
+    round_trip_with_curve(
+        "ExponentialBondingCurve",
+        vec![
+            scrypto_encode(&dec!("1")),     // initial_price
+            scrypto_encode(&dec!("0.001")), // growth_rate
+            scrypto_encode(&384u16),
+        ],
+    );
+}
+
+#[test]
+fn test_7_sigmoid_curve() {
+    round_trip_with_curve(
+        "SigmoidBondingCurve",
+        vec![
+            scrypto_encode(&dec!("5")),         // half_max_price
+            scrypto_encode(&dec!("20000")),     // inflection_supply
+            scrypto_encode(&dec!("100000000")), // steepness
+            scrypto_encode(&384u16),
+        ],
+    );
+}
+
+#[test]
+fn test_8_fees() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut env = TestEnv::new(&mut ledger);
+    let (owner, investor, reserve_def) = setup_fixture(&mut env);
+
+    const BLUEPRINT: &str = "BondingAMM";
+    const CURVE_BLUEPRINT: &str = "BasicBondingCurve";
+
+    // switch to the owner to instantiate a new BondingAMM
+    env.acting_as("owner");
+    let user = owner;
+
+    // create basic curve
+    let mut receipt = env.call_function(CURVE_BLUEPRINT, "new", vec![]);
+    let basic_curve: ComponentAddress = return_of_call_function(&mut receipt, CURVE_BLUEPRINT);
+
+    // instantiate amm with basic curve, 1% buy fee and 2% sell fee
+    let mut receipt = env.call_function_aux(
+        BLUEPRINT,
+        "new_with_fees",
+        vec![
+            get_param_bucket!(dec!(60000u64), reserve_def, env.current_user.unwrap().account),
+            get_param_value!("Continuous"),
+            get_param_value!("XC"),
+            get_param_value!(basic_curve),
+            get_param_value!(100u16),
+            get_param_value!(200u16),
+        ],
+    );
+    println!("new_with_fees: receipt: {:?}", receipt);
+    assert!(receipt.result.is_ok());
+
+    // this is brittle checking the defs based on order...
+    let continuous_addr = receipt.new_resource_addresses[1]; // this should be the CONTINUOUS
+    let owner_badge_addr = receipt.new_resource_addresses[2]; // this should be the owner badge
+
+    // the owner got the badge, and no fee was taken from the initial reserve
+    assert_eq!(env.get_amount_for_rd(user.account, owner_badge_addr), dec!(1u64));
+    assert_eq!(env.get_amount_for_rd(user.account, continuous_addr), dec!(60000u64));
+
+    let ret: (ComponentAddress, Bucket, Bucket) = return_of_call_function(&mut receipt, BLUEPRINT);
+    let amm = ret.0;
+
+    // now switch to an investor
+    env.acting_as("investor");
+    let user = investor;
+
+    // quotes include the fees
+    let mut receipt = env.call_method(amm, "get_buy_quote_amount", vec![scrypto_encode(&dec!("300"))]);
+    assert!(receipt.result.is_ok());
+    let quote: Decimal = return_of_call_method(&mut receipt, "get_buy_quote_amount");
+    assert_eq!(quote, dec!(297u64));
+
+    let mut receipt = env.call_method(amm, "get_sell_quote_amount", vec![scrypto_encode(&dec!("297"))]);
+    assert!(receipt.result.is_ok());
+    let quote: Decimal = return_of_call_method(&mut receipt, "get_sell_quote_amount");
+    assert_eq!(quote, dec!("291.06"));
+
+    // buy 300, 3 go to the treasury
+    let receipt = env.call_method_aux(
+        amm,
+        "buy",
+        vec![
+            get_param_bucket!(dec!(300u64), reserve_def, env.current_user.unwrap().account),
+            get_param_value!(dec!(0u32)),
+        ]
+    );
+    println!("buy: receipt: {:?}", receipt);
+    assert!(receipt.result.is_ok());
+    assert_eq!(env.get_amount_for_rd(user.account, continuous_addr), dec!(297u64));
+
+    // sell it all back, 5.94 go to the treasury
+    let receipt = env.call_method_aux(
+        amm,
+        "sell",
+        vec![
+            get_param_bucket!(dec!(297u64), continuous_addr, env.current_user.unwrap().account),
+            get_param_value!(dec!(0u32)),
+        ],
+    );
+    println!("sell: receipt: {:?}", receipt);
+    assert!(receipt.result.is_ok());
+    assert_eq!(env.get_amount_for_rd(user.account, reserve_def), dec!("999991.06"));
+
+    let mut receipt = env.call_method(amm, "get_treasury_amount", vec![]);
+    assert!(receipt.result.is_ok());
+    let treasury: Decimal = return_of_call_method(&mut receipt, "get_treasury_amount");
+    assert_eq!(treasury, dec!("8.94"));
+
+    // only the owner can withdraw from the treasury
+    let receipt = env.call_method(amm, "withdraw_treasury", vec![scrypto_encode(&dec!("8.94"))]);
+    println!("withdraw_treasury: receipt: {:?}", receipt);
+    assert!(receipt.result.is_err());
+
+    // the owner badge has to be presented as a proof, which needs a transaction signed by its holder,
+    // so move the badge to an account whose key we hold
+    let (holder_pk, holder_sk, holder_account) = env.executor.new_account();
+    env.acting_as("owner");
+    let receipt = env.call_method_aux(
+        holder_account,
+        "deposit",
+        vec![get_param_bucket!(dec!(1u64), owner_badge_addr, env.current_user.unwrap().account)],
+    );
+    println!("deposit owner badge: receipt: {:?}", receipt);
+    assert!(receipt.result.is_ok());
+    assert_eq!(env.get_amount_for_rd(holder_account, reserve_def), dec!(0u64));
+
+    // the owner withdraws the whole treasury with a proof of the badge
+    let tx = TransactionBuilder::new()
+        .create_proof_from_account_by_amount(dec!(1u64), owner_badge_addr, holder_account)
+        .call_method(amm, "withdraw_treasury", args![dec!("8.94")])
+        .call_method_with_all_resources(holder_account, "deposit_batch")
+        .build(env.executor.get_nonce([holder_pk]))
+        .sign([&holder_sk]);
+    let receipt = env.executor.validate_and_execute(&tx).unwrap();
+    println!("withdraw_treasury: receipt: {:?}", receipt);
+    assert!(receipt.result.is_ok());
+    assert_eq!(env.get_amount_for_rd(holder_account, reserve_def), dec!("8.94"));
+
+    let mut receipt = env.call_method(amm, "get_treasury_amount", vec![]);
+    assert!(receipt.result.is_ok());
+    let treasury: Decimal = return_of_call_method(&mut receipt, "get_treasury_amount");
+    assert_eq!(treasury, dec!(0u64));
+}
+
+#[test]
+fn test_9_deadline() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    ledger.set_epoch(5);
+    let mut env = TestEnv::new(&mut ledger);
+    let (_owner, investor, reserve_def) = setup_fixture(&mut env);
+
+    const BLUEPRINT: &str = "BondingAMM";
+    const CURVE_BLUEPRINT: &str = "BasicBondingCurve";
+
+    // switch to the owner to instantiate a new BondingAMM
+    env.acting_as("owner");
+
+    // create basic curve
+    let mut receipt = env.call_function(CURVE_BLUEPRINT, "new", vec![]);
+    let basic_curve: ComponentAddress = return_of_call_function(&mut receipt, CURVE_BLUEPRINT);
+
+    // instantiate amm with basic curve
+    let mut receipt = env.call_function_aux(
+        BLUEPRINT,
+        "new_with_curve",
+        vec![
+            get_param_bucket!(dec!(60000u64), reserve_def, env.current_user.unwrap().account),
+            get_param_value!("Continuous"),
+            get_param_value!("XC"),
+            get_param_value!(basic_curve),
+        ],
+    );
+    println!("new_with_curve: receipt: {:?}", receipt);
+    assert!(receipt.result.is_ok());
+
+    // this is brittle checking the defs based on order...
+    let continuous_addr = receipt.new_resource_addresses[1]; // this should be the CONTINUOUS
+
+    let ret: (ComponentAddress, Bucket, Bucket) = return_of_call_function(&mut receipt, BLUEPRINT);
+    let amm = ret.0;
+
+    // now switch to an investor
+    env.acting_as("investor");
+    let user = investor;
+
+    // the deadline (epoch 4) has already passed
+    let receipt = env.call_method_aux(
+        amm,
+        "buy_with_deadline",
+        vec![
+            get_param_bucket!(dec!(300u64), reserve_def, env.current_user.unwrap().account),
+            get_param_value!(dec!(0u32)),
+            get_param_value!(4u64),
+        ]
+    );
+    println!("buy_with_deadline: receipt: {:?}", receipt);
+    assert!(receipt.result.is_err());
+
+    // the minimum can't be met
+    let receipt = env.call_method_aux(
+        amm,
+        "buy_with_deadline",
+        vec![
+            get_param_bucket!(dec!(300u64), reserve_def, env.current_user.unwrap().account),
+            get_param_value!(dec!(301u32)),
+            get_param_value!(10u64),
+        ]
+    );
+    println!("buy_with_deadline: receipt: {:?}", receipt);
+    assert!(receipt.result.is_err());
+
+    // nothing changed hands
+    assert_eq!(env.get_amount_for_rd(user.account, reserve_def), dec!(1_000_000u64));
+    assert_eq!(env.get_amount_for_rd(user.account, continuous_addr), Decimal::zero());
+
+    // within the deadline and the minimum is met
+    let receipt = env.call_method_aux(
+        amm,
+        "buy_with_deadline",
+        vec![
+            get_param_bucket!(dec!(300u64), reserve_def, env.current_user.unwrap().account),
+            get_param_value!(dec!(300u32)),
+            get_param_value!(5u64),
+        ]
+    );
+    println!("buy_with_deadline: receipt: {:?}", receipt);
+    assert!(receipt.result.is_ok());
+    assert_eq!(env.get_amount_for_rd(user.account, continuous_addr), dec!(300u64));
+
+    // same for selling
+    let receipt = env.call_method_aux(
+        amm,
+        "sell_with_deadline",
+        vec![
+            get_param_bucket!(dec!(300u64), continuous_addr, env.current_user.unwrap().account),
+            get_param_value!(dec!(300u32)),
+            get_param_value!(4u64),
+        ]
+    );
+    println!("sell_with_deadline: receipt: {:?}", receipt);
+    assert!(receipt.result.is_err());
+
+    let receipt = env.call_method_aux(
+        amm,
+        "sell_with_deadline",
+        vec![
+            get_param_bucket!(dec!(300u64), continuous_addr, env.current_user.unwrap().account),
+            get_param_value!(dec!(300u32)),
+            get_param_value!(10u64),
+        ]
+    );
+    println!("sell_with_deadline: receipt: {:?}", receipt);
+    assert!(receipt.result.is_ok());
+    assert_eq!(env.get_amount_for_rd(user.account, reserve_def), dec!(1_000_000u64));
+    assert_eq!(env.get_amount_for_rd(user.account, continuous_addr), Decimal::zero());
+}
+
+#[test]
+fn test_10_curve_migration_requires_owner() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut env = TestEnv::new(&mut ledger);
+    let (_owner, _investor, reserve_def) = setup_fixture(&mut env);
+
+    const BLUEPRINT: &str = "BondingAMM";
+    const CURVE_BLUEPRINT: &str = "BasicBondingCurve";
+
+    // switch to the owner to instantiate a new BondingAMM
+    env.acting_as("owner");
+
+    // create two basic curves, same price everywhere
+    let mut receipt = env.call_function(CURVE_BLUEPRINT, "new", vec![]);
+    let basic_curve: ComponentAddress = return_of_call_function(&mut receipt, CURVE_BLUEPRINT);
+    let mut receipt = env.call_function(CURVE_BLUEPRINT, "new", vec![]);
+    let new_curve: ComponentAddress = return_of_call_function(&mut receipt, CURVE_BLUEPRINT);
+
+    // instantiate amm with basic curve
+    let mut receipt = env.call_function_aux(
+        BLUEPRINT,
+        "new_with_curve",
+        vec![
+            get_param_bucket!(dec!(60000u64), reserve_def, env.current_user.unwrap().account),
+            get_param_value!("Continuous"),
+            get_param_value!("XC"),
+            get_param_value!(basic_curve),
+        ],
+    );
+    println!("new_with_curve: receipt: {:?}", receipt);
+    assert!(receipt.result.is_ok());
+
+    let ret: (ComponentAddress, Bucket, Bucket) = return_of_call_function(&mut receipt, BLUEPRINT);
+    let amm = ret.0;
+
+    // an investor without the owner badge can't migrate the curve
+    env.acting_as("investor");
+
+    let receipt = env.call_method(amm, "propose_curve_migration", vec![scrypto_encode(&new_curve)]);
+    println!("propose_curve_migration: receipt: {:?}", receipt);
+    assert!(receipt.result.is_err());
+
+    let receipt = env.call_method(amm, "execute_curve_migration", vec![]);
+    println!("execute_curve_migration: receipt: {:?}", receipt);
+    assert!(receipt.result.is_err());
+
+    // nothing changed
+    let mut receipt = env.call_method(amm, "get_pending_curve_migration", vec![]);
+    assert!(receipt.result.is_ok());
+    let pending: Option<(ComponentAddress, u64)> = return_of_call_method(&mut receipt, "get_pending_curve_migration");
+    assert_eq!(pending, None);
+
+    let mut receipt = env.call_method(amm, "get_bonding_curve", vec![]);
+    assert!(receipt.result.is_ok());
+    let curve: ComponentAddress = return_of_call_method(&mut receipt, "get_bonding_curve");
+    assert_eq!(curve, basic_curve);
+}
+
+// calls a method of the amm with a proof of the owner badge held by account, signed with its key
+fn call_as_owner<'a, L: SubstateStore>(
+    env: &mut TestEnv<'a, L>,
+    key: (&EcdsaPublicKey, &EcdsaPrivateKey),
+    account: ComponentAddress,
+    owner_badge: ResourceAddress,
+    amm: ComponentAddress,
+    method: &str,
+    args: Vec<Vec<u8>>,
+) -> Receipt {
+    let tx = TransactionBuilder::new()
+        .create_proof_from_account_by_amount(dec!(1u64), owner_badge, account)
+        .call_method(amm, method, args)
+        .call_method_with_all_resources(account, "deposit_batch")
+        .build(env.executor.get_nonce([*key.0]))
+        .sign([key.1]);
+    let receipt = env.executor.validate_and_execute(&tx).unwrap();
+    println!("{}: receipt: {:?}", method, receipt);
+    receipt
+}
+
+#[test]
+fn test_11_curve_migration() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut env = TestEnv::new(&mut ledger);
+    let (_owner, _investor, reserve_def) = setup_fixture(&mut env);
+
+    const BLUEPRINT: &str = "BondingAMM";
+    const CURVE_BLUEPRINT: &str = "BasicBondingCurve";
+
+    // switch to the owner to instantiate a new BondingAMM
+    env.acting_as("owner");
+
+    // two basic curves with a price of 1 everywhere
+    let mut receipt = env.call_function(CURVE_BLUEPRINT, "new", vec![]);
+    let basic_curve: ComponentAddress = return_of_call_function(&mut receipt, CURVE_BLUEPRINT);
+    let mut receipt = env.call_function(CURVE_BLUEPRINT, "new", vec![]);
+    let new_curve: ComponentAddress = return_of_call_function(&mut receipt, CURVE_BLUEPRINT);
+
+    // a linear curve with a price of 0.4 + 0.00001 * 60000 = 1 at the initial supply only
+    let mut receipt = env.call_function(
+        "LinearBondingCurve",
+        "new",
+        vec![
+            scrypto_encode(&dec!("0.00001")), // slope
+            scrypto_encode(&dec!("0.4")),     // initial_price
+            scrypto_encode(&384u16),
+        ],
+    );
+    let linear_curve: ComponentAddress = return_of_call_function(&mut receipt, "LinearBondingCurve");
+
+    // instantiate amm with basic curve, the migration timelock is 100 epochs
+    let mut receipt = env.call_function_aux(
+        BLUEPRINT,
+        "new_with_curve",
+        vec![
+            get_param_bucket!(dec!(60000u64), reserve_def, env.current_user.unwrap().account),
+            get_param_value!("Continuous"),
+            get_param_value!("XC"),
+            get_param_value!(basic_curve),
+        ],
+    );
+    println!("new_with_curve: receipt: {:?}", receipt);
+    assert!(receipt.result.is_ok());
+    let owner_badge_addr = receipt.new_resource_addresses[2]; // this should be the owner badge
+
+    let ret: (ComponentAddress, Bucket, Bucket) = return_of_call_function(&mut receipt, BLUEPRINT);
+    let amm = ret.0;
+
+    // move the owner badge to an account whose key we hold, see test_8_fees
+    let (holder_pk, holder_sk, holder_account) = env.executor.new_account();
+    let receipt = env.call_method_aux(
+        holder_account,
+        "deposit",
+        vec![get_param_bucket!(dec!(1u64), owner_badge_addr, env.current_user.unwrap().account)],
+    );
+    assert!(receipt.result.is_ok());
+    let key = (&holder_pk, &holder_sk);
+
+    // propose at epoch 0, the migration can be executed from epoch 100
+    env.executor.substate_store_mut().set_epoch(0);
+    let receipt = call_as_owner(&mut env, key, holder_account, owner_badge_addr, amm, "propose_curve_migration", args![new_curve]);
+    assert!(receipt.result.is_ok());
+
+    let mut receipt = env.call_method(amm, "get_pending_curve_migration", vec![]);
+    let pending: Option<(ComponentAddress, u64)> = return_of_call_method(&mut receipt, "get_pending_curve_migration");
+    assert_eq!(pending, Some((new_curve, 100)));
+
+    // still timelocked
+    let receipt = call_as_owner(&mut env, key, holder_account, owner_badge_addr, amm, "execute_curve_migration", vec![]);
+    assert!(receipt.result.is_err());
+    env.executor.substate_store_mut().set_epoch(99);
+    let receipt = call_as_owner(&mut env, key, holder_account, owner_badge_addr, amm, "execute_curve_migration", vec![]);
+    assert!(receipt.result.is_err());
+
+    // the timelock has passed
+    env.executor.substate_store_mut().set_epoch(100);
+    let receipt = call_as_owner(&mut env, key, holder_account, owner_badge_addr, amm, "execute_curve_migration", vec![]);
+    assert!(receipt.result.is_ok());
+
+    let mut receipt = env.call_method(amm, "get_bonding_curve", vec![]);
+    let curve: ComponentAddress = return_of_call_method(&mut receipt, "get_bonding_curve");
+    assert_eq!(curve, new_curve);
+    let mut receipt = env.call_method(amm, "get_pending_curve_migration", vec![]);
+    let pending: Option<(ComponentAddress, u64)> = return_of_call_method(&mut receipt, "get_pending_curve_migration");
+    assert_eq!(pending, None);
+
+    // a cancelled proposal is cleared
+    let receipt = call_as_owner(&mut env, key, holder_account, owner_badge_addr, amm, "propose_curve_migration", args![linear_curve]);
+    assert!(receipt.result.is_ok());
+    let receipt = call_as_owner(&mut env, key, holder_account, owner_badge_addr, amm, "cancel_curve_migration", vec![]);
+    assert!(receipt.result.is_ok());
+    let mut receipt = env.call_method(amm, "get_pending_curve_migration", vec![]);
+    let pending: Option<(ComponentAddress, u64)> = return_of_call_method(&mut receipt, "get_pending_curve_migration");
+    assert_eq!(pending, None);
+
+    // propose again at epoch 100, then a buy moves the supply so the linear curve no longer gives the same price
+    let receipt = call_as_owner(&mut env, key, holder_account, owner_badge_addr, amm, "propose_curve_migration", args![linear_curve]);
+    assert!(receipt.result.is_ok());
+
+    env.acting_as("investor");
+    let receipt = env.call_method_aux(
+        amm,
+        "buy",
+        vec![
+            get_param_bucket!(dec!(300u64), reserve_def, env.current_user.unwrap().account),
+            get_param_value!(dec!(0u32)),
+        ]
+    );
+    assert!(receipt.result.is_ok());
+
+    // past the timelock, but the price at the current supply is 1.003 instead of 1
+    env.executor.substate_store_mut().set_epoch(200);
+    let receipt = call_as_owner(&mut env, key, holder_account, owner_badge_addr, amm, "execute_curve_migration", vec![]);
+    assert!(receipt.result.is_err());
+
+    let mut receipt = env.call_method(amm, "get_bonding_curve", vec![]);
+    let curve: ComponentAddress = return_of_call_method(&mut receipt, "get_bonding_curve");
+    assert_eq!(curve, new_curve);
+}
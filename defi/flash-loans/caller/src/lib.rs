@@ -1,5 +1,14 @@
 use scrypto::prelude::*;
 
+use bonding_macros::blueprint_stub; // generates a typed stub for calling the FlashLoan component
+
+// the methods of the FlashLoan blueprint used by the caller
+#[blueprint_stub]
+pub trait FlashLoan {
+    fn request_loan(&mut self, amount: Decimal, resource_address: ResourceAddress) -> (Bucket, Bucket);
+    fn repay(&mut self, repayment: Bucket, receipt: Bucket) -> Bucket;
+}
+
 /*
  *  This is an example of a component that is requesting a
  *  flash loan from another component to make more XRD and repay the loan
@@ -29,15 +38,19 @@ blueprint! {
             .globalize()
         }
 
-        pub fn call(&self, this_component_address: ComponentAddress) -> Bucket {
-            // Get a loan of 1000 XRD
+        pub fn call(&mut self) -> Bucket {
+            // Get a loan of 1000 XRD, along with the receipt which has to be returned to repay it
             let amount:Decimal = dec!("1000");
+            let loaner: FlashLoan = self.loaner_component.into();
+            let (loan, receipt) = loaner.request_loan(amount, RADIX_TOKEN);
+
+            info!("Received {} from loaner !", loan.amount());
+            info!("Found opportunity to make {} XRD !", self.opportunity.amount());
+
+            let opportunity_result = self.get_opportunity(loan);
 
-            // Call request loan and return the change back to the user
-            borrow_component!(self.loaner_component).call::<Bucket>("request_loan", args![
-                amount, 
-                this_component_address
-            ]).into()
+            // Repay the loan and return the change back to the user
+            loaner.repay(opportunity_result, receipt)
         }
 
         // Used to simulate an opportunity to make some XRD by paying 1000 XRD
@@ -47,15 +60,5 @@ blueprint! {
 
             self.opportunity.take_all()
         }
-
-        pub fn execute(&mut self, loan: Bucket) -> Bucket {
-            info!("Received {} from loaner !", loan.amount());
-            info!("Found opportunity to make {} XRD !", self.opportunity.amount());
-
-            let opportunity_result = self.get_opportunity(loan);
-
-            // Repay the loan (you get the change back)
-            opportunity_result
-        }
     }
 }
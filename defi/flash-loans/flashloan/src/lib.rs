@@ -1,39 +1,263 @@
 use scrypto::prelude::*;
 
+/*
+ * Terms of a loan, held by the receipt handed out with it
+ */
+#[derive(NonFungibleData)]
+pub struct LoanReceipt {
+    resource_address: ResourceAddress,
+    amount: Decimal,
+    interest: Decimal
+}
+
+/*
+ * Divisibility of a fungible resource
+ */
+fn divisibility(resource_address: ResourceAddress) -> u8 {
+    match borrow_resource_manager!(resource_address).resource_type() {
+        ResourceType::Fungible { divisibility } => divisibility,
+        ResourceType::NonFungible => panic!("Non fungible resources can't be loaned")
+    }
+}
+
 blueprint! {
     struct FlashLoan {
         interest: Decimal,
-        vault: Vault
+        vaults: HashMap<ResourceAddress, Vault>,
+        // Amount of every resource currently out on loan, still owned by the depositors
+        lent: HashMap<ResourceAddress, Decimal>,
+        // Share token of every resource, and the resource of every share token
+        share_tokens: HashMap<ResourceAddress, ResourceAddress>,
+        share_resources: HashMap<ResourceAddress, ResourceAddress>,
+        // Badge allowed to mint and burn loan receipts and share tokens
+        minter: Vault,
+        loan_receipt: ResourceAddress,
+        paused: bool
     }
 
     impl FlashLoan {
         /*
-         * Generate a FlashLoan component with a specified interest and a specified bucket as funds
+         * Generate a FlashLoan component with a specified interest and the specified buckets as funds,
+         * which can be of different resources.
+         * Returns the admin badge, allowed to change the interest and pause borrowing, and the share tokens of the funds
          */
-        pub fn new(tokens: Bucket, interest: Decimal) -> ComponentAddress {
-            Self {
-                vault: Vault::with_bucket(tokens),
+        pub fn new(tokens: Vec<Bucket>, interest: Decimal) -> (ComponentAddress, Bucket, Vec<Bucket>) {
+            assert!(interest >= Decimal::zero(), "The interest can't be negative");
+
+            let minter = ResourceBuilder::new_fungible()
+                .divisibility(DIVISIBILITY_NONE)
+                .metadata("name", "Flash loan minter")
+                .initial_supply(1);
+
+            let admin_badge = ResourceBuilder::new_fungible()
+                .divisibility(DIVISIBILITY_NONE)
+                .metadata("name", "Flash loan admin badge")
+                .initial_supply(1);
+
+            // Receipts can never be deposited, the only way to get rid of one is to repay the loan
+            let loan_receipt = ResourceBuilder::new_non_fungible()
+                .metadata("name", "Flash loan receipt - must be repaid to be burned")
+                .mintable(rule!(require(minter.resource_address())), LOCKED)
+                .burnable(rule!(require(minter.resource_address())), LOCKED)
+                .restrict_deposit(rule!(deny_all), LOCKED)
+                .no_initial_supply();
+
+            let mut flash_loan = Self {
                 interest: interest,
+                vaults: HashMap::new(),
+                lent: HashMap::new(),
+                share_tokens: HashMap::new(),
+                share_resources: HashMap::new(),
+                minter: Vault::with_bucket(minter),
+                loan_receipt: loan_receipt,
+                paused: false
+            };
+
+            let shares: Vec<Bucket> = tokens.into_iter().map(|bucket| flash_loan.deposit(bucket)).collect();
+
+            let access_rules = AccessRules::new()
+                .method("set_interest", rule!(require(admin_badge.resource_address())))
+                .method("pause", rule!(require(admin_badge.resource_address())))
+                .method("unpause", rule!(require(admin_badge.resource_address())))
+                .default(rule!(allow_all));
+
+            let component = flash_loan
+                .instantiate()
+                .add_access_check(access_rules)
+                .globalize();
+
+            (component, admin_badge, shares)
+        }
+
+        /*
+         * Deposit tokens to be loaned, in exchange for share tokens of that resource.
+         * Shares grow in value with the interest paid by borrowers
+         */
+        pub fn deposit(&mut self, tokens: Bucket) -> Bucket {
+            let resource_address = tokens.resource_address();
+            assert!(resource_address != self.loan_receipt, "Loan receipts can't be deposited");
+            assert!(!self.share_resources.contains_key(&resource_address), "Share tokens can't be deposited");
+
+            if !self.vaults.contains_key(&resource_address) {
+                let share_token = ResourceBuilder::new_fungible()
+                    .divisibility(DIVISIBILITY_MAXIMUM)
+                    .metadata("name", "Flash loan share")
+                    .mintable(rule!(require(self.minter.resource_address())), LOCKED)
+                    .burnable(rule!(require(self.minter.resource_address())), LOCKED)
+                    .no_initial_supply();
+                self.vaults.insert(resource_address, Vault::new(resource_address));
+                self.lent.insert(resource_address, Decimal::zero());
+                self.share_tokens.insert(resource_address, share_token);
+                self.share_resources.insert(share_token, resource_address);
             }
-            .instantiate()
-            .globalize()
+
+            let share_token = self.share_tokens[&resource_address];
+            let share_supply = borrow_resource_manager!(share_token).total_supply();
+            let total = self.total_liquidity(resource_address);
+
+            let shares = if share_supply.is_zero() || total.is_zero() {
+                tokens.amount()
+            } else {
+                tokens.amount() * share_supply / total
+            };
+
+            self.vaults.get_mut(&resource_address).unwrap().put(tokens);
+            self.minter.authorize(|| borrow_resource_manager!(share_token).mint(shares))
+        }
+
+        /*
+         * Withdraw the tokens owned by share tokens, limited to the liquidity which isn't out on loan
+         */
+        pub fn withdraw(&mut self, shares: Bucket) -> Bucket {
+            let resource_address = match self.share_resources.get(&shares.resource_address()) {
+                Some(resource_address) => *resource_address,
+                None => panic!("These are not share tokens of this component")
+            };
+
+            let share_supply = borrow_resource_manager!(shares.resource_address()).total_supply();
+            // Rounded down to what the resource can hold
+            let amount = (shares.amount() * self.total_liquidity(resource_address) / share_supply)
+                .round(divisibility(resource_address), RoundingMode::TowardsNegativeInfinity);
+
+            let vault = self.vaults.get_mut(&resource_address).unwrap();
+            assert!(
+                amount <= vault.amount(),
+                "Only {} is available to withdraw, the rest is out on loan", vault.amount()
+            );
+
+            self.minter.authorize(|| shares.burn());
+            vault.take(amount)
+        }
+
+        /*
+         * Lend an amount of a resource, together with a receipt for it. The receipt can't be deposited,
+         * so the transaction fails unless the loan is repaid with `repay`, which burns it.
+         * Several loans, of the same or different resources, can be taken in a single transaction
+         */
+        pub fn request_loan(&mut self, amount: Decimal, resource_address: ResourceAddress) -> (Bucket, Bucket) {
+            assert!(!self.paused, "Borrowing is paused");
+
+            let vault = match self.vaults.get_mut(&resource_address) {
+                Some(vault) => vault,
+                None => panic!("This resource can't be loaned")
+            };
+            assert!(amount < vault.amount(), "Not enough funds to loan");
+
+            let loan = vault.take(amount);
+            let lent = self.lent[&resource_address] + amount;
+            self.lent.insert(resource_address, lent);
+
+            let receipt = self.minter.authorize(|| {
+                borrow_resource_manager!(self.loan_receipt).mint_non_fungible(
+                    &NonFungibleId::random(),
+                    LoanReceipt {
+                        resource_address: resource_address,
+                        amount: amount,
+                        // Rounded up to what the loaned resource can hold, so the loan can be repaid exactly
+                        interest: (amount * self.interest / 100)
+                            .round(divisibility(resource_address), RoundingMode::TowardsPositiveInfinity)
+                    }
+                )
+            });
+
+            (loan, receipt)
         }
 
-        pub fn request_loan(&mut self, amount: Decimal, component_address: ComponentAddress) -> Bucket {
-            assert!(amount < self.vault.amount(), "Not enough funds to loan");
+        /*
+         * Repay a loan and burn its receipt, the change is returned.
+         * The interest goes to the depositors of the resource
+         */
+        pub fn repay(&mut self, mut repayment: Bucket, receipt: Bucket) -> Bucket {
+            assert!(receipt.resource_address() == self.loan_receipt, "This is not a loan receipt");
+            let terms = receipt.non_fungible::<LoanReceipt>().data();
 
-            // Call the execute method at the specified component's address with the requested funds
-            let args = args![self.vault.take(amount)];
-            let mut returned_bucket: Bucket = borrow_component!(component_address).call::<Bucket>("execute", args).into();
+            assert!(
+                repayment.resource_address() == terms.resource_address,
+                "The loan has to be repaid with the resource that was loaned"
+            );
 
             // Make sure they repaid in loan in full
-            let amount_to_take = amount * ((self.interest / 100) + 1);
-            assert!(returned_bucket.amount() >= amount_to_take, "You have to return more than {}", amount_to_take);
+            let amount_to_take = terms.amount + terms.interest;
+            assert!(repayment.amount() >= amount_to_take, "You have to return more than {}", amount_to_take);
+
+            self.vaults.get_mut(&terms.resource_address).unwrap().put(repayment.take(amount_to_take));
+            let lent = self.lent[&terms.resource_address] - terms.amount;
+            self.lent.insert(terms.resource_address, lent);
+            self.minter.authorize(|| receipt.burn());
+
+            // Return the change
+            repayment
+        }
+
+        /*
+         * Set the interest, in percent of the loaned amount, of the next loans
+         */
+        pub fn set_interest(&mut self, interest: Decimal) {
+            assert!(interest >= Decimal::zero(), "The interest can't be negative");
+            self.interest = interest;
+        }
 
-            self.vault.put(returned_bucket.take(amount_to_take));
+        pub fn pause(&mut self) {
+            self.paused = true;
+        }
+
+        pub fn unpause(&mut self) {
+            self.paused = false;
+        }
+
+        /*
+         * Amount of a resource which can be loaned or withdrawn right now
+         */
+        pub fn available_liquidity(&self, resource_address: ResourceAddress) -> Decimal {
+            match self.vaults.get(&resource_address) {
+                Some(vault) => vault.amount(),
+                None => Decimal::zero()
+            }
+        }
+
+        /*
+         * Amount of a resource owned by a single share token
+         */
+        pub fn share_price(&self, resource_address: ResourceAddress) -> Decimal {
+            let share_token = match self.share_tokens.get(&resource_address) {
+                Some(share_token) => *share_token,
+                None => panic!("This resource can't be loaned")
+            };
+            let share_supply = borrow_resource_manager!(share_token).total_supply();
+            if share_supply.is_zero() {
+                Decimal::one()
+            } else {
+                self.total_liquidity(resource_address) / share_supply
+            }
+        }
+
+        pub fn share_token(&self, resource_address: ResourceAddress) -> ResourceAddress {
+            self.share_tokens[&resource_address]
+        }
 
-            // Return the change back to the component
-            return returned_bucket;
+        // Liquidity owned by the depositors, both in the vault and out on loan
+        fn total_liquidity(&self, resource_address: ResourceAddress) -> Decimal {
+            self.vaults[&resource_address].amount() + self.lent[&resource_address]
         }
     }
 }
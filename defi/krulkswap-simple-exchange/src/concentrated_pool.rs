@@ -0,0 +1,820 @@
+use scrypto::prelude::*;
+use std::collections::BTreeMap;
+
+// Concentrated liquidity lets LPs provide liquidity within a price range only.
+// Inside its range, a position works like a constant product pool with much bigger balances,
+// so stable pairs can be traded with little slippage using far less capital.
+//
+// The price is the amount of token B one token A is worth. The price range is divided in ticks,
+// every tick moves the price by 0.01%, and positions start and end on ticks.
+// The pool keeps the square root of the price, and the liquidity L of the positions in range,
+// in which the balances of a range are:
+// a = L * (1 / sqrt(price) - 1 / sqrt(upper price))
+// b = L * (sqrt(price) - sqrt(lower price))
+// When a swap moves the price past the tick where a position starts or ends, the pool "crosses" the tick,
+// adding or removing the liquidity of that position.
+//
+// All the math rounds in favour of the pool, leaving the rounding dust in it.
+
+// The lowest and highest ticks, which are prices of about 0.000000002 and 480000000.
+pub const MIN_TICK: i32 = -200_000;
+pub const MAX_TICK: i32 = 200_000;
+
+// The square root of the price change of a tick, sqrt(1.0001).
+fn tick_base() -> Decimal {
+    dec!("1.000049998750062496")
+}
+
+// A little extra taken on deposits and swap inputs, to make up for the amounts being rounded down.
+fn rounding() -> Decimal {
+    dec!("0.0000000000000001")
+}
+
+// Square root by Newton's method, rounded down.
+pub fn sqrt(x: Decimal) -> Decimal {
+    assert!(!x.is_negative(), "Cannot take the square root of a negative number");
+    if x.is_zero() {
+        return x;
+    }
+    // Start above the root, so every step moves down to it.
+    let mut y = std::cmp::max(x, Decimal::one());
+    loop {
+        let next = (y + x / y) / Decimal::from(2);
+        if next >= y {
+            return y;
+        }
+        y = next;
+    }
+}
+
+// Get the square root of the price at a tick: sqrt(1.0001)^tick
+pub fn sqrt_price_at_tick(tick: i32) -> Decimal {
+    assert!(
+        tick >= MIN_TICK && tick <= MAX_TICK,
+        "Tick {} is outside of the range of {} to {}",
+        tick,
+        MIN_TICK,
+        MAX_TICK
+    );
+    // Exponentiation by squaring.
+    let mut result = Decimal::one();
+    let mut base = tick_base();
+    let mut exponent = tick.unsigned_abs();
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = result * base;
+        }
+        base = base * base;
+        exponent >>= 1;
+    }
+    if tick < 0 {
+        Decimal::one() / result
+    } else {
+        result
+    }
+}
+
+// Get the highest tick at or below a square root of the price.
+pub fn tick_at_sqrt_price(sqrt_price: Decimal) -> i32 {
+    assert!(
+        sqrt_price >= sqrt_price_at_tick(MIN_TICK) && sqrt_price <= sqrt_price_at_tick(MAX_TICK),
+        "The price is outside of the range of the pool"
+    );
+    let (mut low, mut high) = (MIN_TICK, MAX_TICK);
+    while low < high {
+        let middle = low + (high - low + 1) / 2;
+        if sqrt_price_at_tick(middle) <= sqrt_price {
+            low = middle;
+        } else {
+            high = middle - 1;
+        }
+    }
+    low
+}
+
+// Amount of token A for liquidity between two square root prices: L * (upper - lower) / (upper * lower)
+pub fn amount_a(liquidity: Decimal, sqrt_lower: Decimal, sqrt_upper: Decimal) -> Decimal {
+    liquidity * (sqrt_upper - sqrt_lower) / sqrt_upper / sqrt_lower
+}
+
+// Amount of token B for liquidity between two square root prices: L * (upper - lower)
+pub fn amount_b(liquidity: Decimal, sqrt_lower: Decimal, sqrt_upper: Decimal) -> Decimal {
+    liquidity * (sqrt_upper - sqrt_lower)
+}
+
+// Liquidity of all the positions starting or ending at a tick.
+#[derive(TypeId, Encode, Decode, Describe)]
+pub struct Tick {
+    // Liquidity of all positions using the tick, the tick is removed when this gets to zero.
+    pub liquidity_gross: Decimal,
+    // Liquidity added when crossing the tick going up, and removed going down.
+    pub liquidity_net: Decimal,
+    // Fees per liquidity earned on the other side of the tick than the current price, in both tokens.
+    pub fee_growth_outside_a: Decimal,
+    pub fee_growth_outside_b: Decimal,
+}
+
+// The state of a concentrated liquidity pool, without the vaults.
+#[derive(TypeId, Encode, Decode, Describe)]
+pub struct ConcentratedLiquidity {
+    pub sqrt_price: Decimal,
+    // The tick of the current price, ticks at or below it have been crossed going up.
+    pub current_tick: i32,
+    // The liquidity of all the positions in range.
+    pub liquidity: Decimal,
+    pub fee: Decimal,
+    // Positions can only start and end on ticks that are a multiple of this.
+    pub tick_spacing: i32,
+    // Fees per liquidity earned over the whole life of the pool, in both tokens.
+    pub fee_growth_a: Decimal,
+    pub fee_growth_b: Decimal,
+    // Only the ticks where positions start or end.
+    pub ticks: BTreeMap<i32, Tick>,
+}
+
+impl ConcentratedLiquidity {
+    pub fn new(price: Decimal, fee: Decimal, tick_spacing: i32) -> Self {
+        assert!(price.is_positive(), "The price must be positive");
+        assert!(
+            fee >= Decimal::zero() && fee < Decimal::one(),
+            "The fee must be at least 0 and less than 1"
+        );
+        assert!(tick_spacing > 0, "The tick spacing must be positive");
+        let sqrt_price = sqrt(price);
+        Self {
+            sqrt_price,
+            current_tick: tick_at_sqrt_price(sqrt_price),
+            liquidity: Decimal::zero(),
+            fee,
+            tick_spacing,
+            fee_growth_a: Decimal::zero(),
+            fee_growth_b: Decimal::zero(),
+            ticks: BTreeMap::new(),
+        }
+    }
+
+    pub fn price(&self) -> Decimal {
+        self.sqrt_price * self.sqrt_price
+    }
+
+    fn assert_range(&self, lower_tick: i32, upper_tick: i32) {
+        assert!(lower_tick < upper_tick, "The lower tick must be below the upper tick");
+        assert!(
+            lower_tick > MIN_TICK && upper_tick < MAX_TICK,
+            "The ticks must be between {} and {}",
+            MIN_TICK,
+            MAX_TICK
+        );
+        assert!(
+            lower_tick % self.tick_spacing == 0 && upper_tick % self.tick_spacing == 0,
+            "The ticks must be a multiple of {}",
+            self.tick_spacing
+        );
+    }
+
+    fn in_range(&self, lower_tick: i32, upper_tick: i32) -> bool {
+        lower_tick <= self.current_tick && self.current_tick < upper_tick
+    }
+
+    // Get the balances of liquidity in a range at the current price, rounded down.
+    pub fn amounts(&self, lower_tick: i32, upper_tick: i32, liquidity: Decimal) -> (Decimal, Decimal) {
+        let sqrt_lower = sqrt_price_at_tick(lower_tick);
+        let sqrt_upper = sqrt_price_at_tick(upper_tick);
+        // Below the range everything is token A, above it everything is token B.
+        let sqrt_price = std::cmp::min(std::cmp::max(self.sqrt_price, sqrt_lower), sqrt_upper);
+        (
+            amount_a(liquidity, sqrt_price, sqrt_upper),
+            amount_b(liquidity, sqrt_lower, sqrt_price),
+        )
+    }
+
+    // Get the most liquidity that can be added to a range with the given amounts of tokens.
+    pub fn liquidity_for_amounts(&self, lower_tick: i32, upper_tick: i32, a_amount: Decimal, b_amount: Decimal) -> Decimal {
+        self.assert_range(lower_tick, upper_tick);
+        let sqrt_lower = sqrt_price_at_tick(lower_tick);
+        let sqrt_upper = sqrt_price_at_tick(upper_tick);
+        // Leave room to round the deposit up.
+        let a_amount = std::cmp::max(a_amount - rounding(), Decimal::zero());
+        let b_amount = std::cmp::max(b_amount - rounding(), Decimal::zero());
+        if self.sqrt_price <= sqrt_lower {
+            a_amount * sqrt_lower / (sqrt_upper - sqrt_lower) * sqrt_upper
+        } else if self.sqrt_price >= sqrt_upper {
+            b_amount / (sqrt_upper - sqrt_lower)
+        } else {
+            std::cmp::min(
+                a_amount * self.sqrt_price / (sqrt_upper - self.sqrt_price) * sqrt_upper,
+                b_amount / (self.sqrt_price - sqrt_lower),
+            )
+        }
+    }
+
+    // Add liquidity to a range.
+    // Returns the amounts of both tokens to deposit, rounded up.
+    pub fn add_liquidity(&mut self, lower_tick: i32, upper_tick: i32, liquidity: Decimal) -> (Decimal, Decimal) {
+        self.assert_range(lower_tick, upper_tick);
+        assert!(liquidity.is_positive(), "The liquidity must be positive");
+        self.update_tick(lower_tick, liquidity, false);
+        self.update_tick(upper_tick, liquidity, true);
+        if self.in_range(lower_tick, upper_tick) {
+            self.liquidity += liquidity;
+        }
+        let (a_amount, b_amount) = self.amounts(lower_tick, upper_tick, liquidity);
+        let round_up = |amount: Decimal| if amount.is_zero() { amount } else { amount + rounding() };
+        (round_up(a_amount), round_up(b_amount))
+    }
+
+    // Remove liquidity from a range.
+    // Returns the amounts of both tokens to withdraw, rounded down.
+    pub fn remove_liquidity(&mut self, lower_tick: i32, upper_tick: i32, liquidity: Decimal) -> (Decimal, Decimal) {
+        self.update_tick(lower_tick, -liquidity, false);
+        self.update_tick(upper_tick, -liquidity, true);
+        if self.in_range(lower_tick, upper_tick) {
+            self.liquidity -= liquidity;
+        }
+        self.amounts(lower_tick, upper_tick, liquidity)
+    }
+
+    // Add (positive) or remove (negative) liquidity of a position starting or ending (upper) at a tick.
+    fn update_tick(&mut self, tick: i32, liquidity: Decimal, upper: bool) {
+        let below = tick <= self.current_tick;
+        let (fee_growth_a, fee_growth_b) = (self.fee_growth_a, self.fee_growth_b);
+        let info = self.ticks.entry(tick).or_insert_with(|| {
+            // All the fees until now are counted as earned below the current price.
+            if below {
+                Tick {
+                    liquidity_gross: Decimal::zero(),
+                    liquidity_net: Decimal::zero(),
+                    fee_growth_outside_a: fee_growth_a,
+                    fee_growth_outside_b: fee_growth_b,
+                }
+            } else {
+                Tick {
+                    liquidity_gross: Decimal::zero(),
+                    liquidity_net: Decimal::zero(),
+                    fee_growth_outside_a: Decimal::zero(),
+                    fee_growth_outside_b: Decimal::zero(),
+                }
+            }
+        });
+        info.liquidity_gross += liquidity;
+        // The liquidity of a position is in range above its lower tick, and below its upper tick.
+        if upper {
+            info.liquidity_net -= liquidity;
+        } else {
+            info.liquidity_net += liquidity;
+        }
+        if info.liquidity_gross.is_zero() {
+            self.ticks.remove(&tick);
+        }
+    }
+
+    // Get the fees per liquidity earned within a range, in both tokens.
+    pub fn fee_growth_inside(&self, lower_tick: i32, upper_tick: i32) -> (Decimal, Decimal) {
+        let lower = &self.ticks[&lower_tick];
+        let upper = &self.ticks[&upper_tick];
+        let (below_a, below_b) = if self.current_tick >= lower_tick {
+            (lower.fee_growth_outside_a, lower.fee_growth_outside_b)
+        } else {
+            (self.fee_growth_a - lower.fee_growth_outside_a, self.fee_growth_b - lower.fee_growth_outside_b)
+        };
+        let (above_a, above_b) = if self.current_tick < upper_tick {
+            (upper.fee_growth_outside_a, upper.fee_growth_outside_b)
+        } else {
+            (self.fee_growth_a - upper.fee_growth_outside_a, self.fee_growth_b - upper.fee_growth_outside_b)
+        };
+        (self.fee_growth_a - below_a - above_a, self.fee_growth_b - below_b - above_b)
+    }
+
+    // Cross a tick, updating the liquidity in range and the fees earned outside of it.
+    fn cross_tick(&mut self, tick: i32, going_up: bool) {
+        let info = self.ticks.get_mut(&tick).unwrap();
+        info.fee_growth_outside_a = self.fee_growth_a - info.fee_growth_outside_a;
+        info.fee_growth_outside_b = self.fee_growth_b - info.fee_growth_outside_b;
+        if going_up {
+            self.liquidity += info.liquidity_net;
+            self.current_tick = tick;
+        } else {
+            self.liquidity -= info.liquidity_net;
+            self.current_tick = tick - 1;
+        }
+    }
+
+    // Share a fee out over the liquidity in range.
+    fn earn_fee(&mut self, a_to_b: bool, fee: Decimal) {
+        if a_to_b {
+            self.fee_growth_a += fee / self.liquidity;
+        } else {
+            self.fee_growth_b += fee / self.liquidity;
+        }
+    }
+
+    // Swap an amount of token A for token B (a_to_b), or token B for token A.
+    // The swap goes through the liquidity one range at a time, crossing the ticks in between.
+    // Returns the amount of the input used, which is less than the amount when the liquidity runs out, and the output.
+    pub fn swap(&mut self, a_to_b: bool, amount: Decimal) -> (Decimal, Decimal) {
+        let one_minus_fee = Decimal::one() - self.fee;
+        let mut remaining = amount;
+        let mut output = Decimal::zero();
+        while remaining.is_positive() {
+            // Find the next tick in the direction of the swap, selling A lowers the price.
+            // Past the last tick there is no liquidity left.
+            let next_tick = if a_to_b {
+                self.ticks.range(..=self.current_tick).next_back()
+            } else {
+                self.ticks.range(self.current_tick + 1..).next()
+            };
+            let next_tick = match next_tick {
+                Some((&tick, _)) => tick,
+                None => break,
+            };
+            let target = sqrt_price_at_tick(next_tick);
+
+            // Without liquidity in this range, the price moves to the next tick without a trade.
+            if self.sqrt_price != target && self.liquidity.is_positive() {
+                let liquidity = self.liquidity;
+                let sqrt_price = self.sqrt_price;
+                // The input needed to move the price all the way to the next tick.
+                let step_input = rounding() + if a_to_b {
+                    amount_a(liquidity, target, sqrt_price)
+                } else {
+                    amount_b(liquidity, sqrt_price, target)
+                };
+                let input = remaining * one_minus_fee;
+                if input < step_input {
+                    // The swap ends before the next tick.
+                    let new_sqrt_price = if a_to_b {
+                        std::cmp::min(liquidity * sqrt_price / (liquidity + input * sqrt_price) + rounding(), sqrt_price)
+                    } else {
+                        std::cmp::min(sqrt_price + input / liquidity, target)
+                    };
+                    output += if a_to_b {
+                        amount_b(liquidity, new_sqrt_price, sqrt_price)
+                    } else {
+                        amount_a(liquidity, sqrt_price, new_sqrt_price)
+                    };
+                    self.earn_fee(a_to_b, remaining - input);
+                    self.sqrt_price = new_sqrt_price;
+                    // Stay on this side of the next tick, as it hasn't been crossed.
+                    self.current_tick = if a_to_b {
+                        std::cmp::max(tick_at_sqrt_price(new_sqrt_price), next_tick)
+                    } else {
+                        std::cmp::min(tick_at_sqrt_price(new_sqrt_price), next_tick - 1)
+                    };
+                    remaining = Decimal::zero();
+                    break;
+                }
+                // The swap moves the price to the next tick.
+                output += if a_to_b {
+                    amount_b(liquidity, target, sqrt_price)
+                } else {
+                    amount_a(liquidity, sqrt_price, target)
+                };
+                let step_amount = step_input / one_minus_fee;
+                self.earn_fee(a_to_b, step_amount - step_input);
+                remaining -= step_amount;
+            }
+            self.sqrt_price = target;
+            self.cross_tick(next_tick, !a_to_b);
+        }
+        (amount - remaining, output)
+    }
+}
+
+// The data of a liquidity position NFT.
+#[derive(NonFungibleData)]
+pub struct Position {
+    pub lower_tick: i32,
+    pub upper_tick: i32,
+    #[scrypto(mutable)]
+    pub liquidity: Decimal,
+    // The fees per liquidity earned within the range, when the fees of the position were last updated.
+    #[scrypto(mutable)]
+    pub fee_growth_inside_a: Decimal,
+    #[scrypto(mutable)]
+    pub fee_growth_inside_b: Decimal,
+    #[scrypto(mutable)]
+    pub uncollected_fees_a: Decimal,
+    #[scrypto(mutable)]
+    pub uncollected_fees_b: Decimal,
+}
+
+impl Position {
+    // Add the fees earned since the last update to the uncollected fees.
+    pub fn update_fees(&mut self, state: &ConcentratedLiquidity) {
+        let (fee_growth_inside_a, fee_growth_inside_b) = state.fee_growth_inside(self.lower_tick, self.upper_tick);
+        self.uncollected_fees_a += self.liquidity * (fee_growth_inside_a - self.fee_growth_inside_a);
+        self.uncollected_fees_b += self.liquidity * (fee_growth_inside_b - self.fee_growth_inside_b);
+        self.fee_growth_inside_a = fee_growth_inside_a;
+        self.fee_growth_inside_b = fee_growth_inside_b;
+    }
+}
+
+// A pool where every LP chooses the price range of their liquidity, getting a position NFT for it.
+// This is an alternative to the Pool blueprint, whose LP-tokens cover the whole price range.
+blueprint! {
+    struct ConcentratedPool {
+        // Vault of token A, the price is the amount of token B one token A is worth.
+        a_vault: Vault,
+        // Vault of token B.
+        b_vault: Vault,
+        // Internal badge to mint, update and burn the position NFTs.
+        position_badge: Vault,
+        // ResourceAddress of the position NFTs.
+        position_token: ResourceAddress,
+        // The price, liquidity, ticks and fees of the pool.
+        state: ConcentratedLiquidity
+    }
+
+    impl ConcentratedPool {
+        // Instantiate a ConcentratedPool for a token pair, starting at a price of B per A.
+        // The fee is taken from the input of every swap, e.g. 0.0005 for 0.05%, and goes to the positions in range.
+        // Positions can only start and end on ticks that are a multiple of tick_spacing,
+        // a tick spacing of 10 gives ranges in steps of about 0.1%.
+        pub fn new_pool(
+            a_address: ResourceAddress,
+            b_address: ResourceAddress,
+            price: Decimal,
+            fee: Decimal,
+            tick_spacing: i32
+        ) -> ComponentAddress {
+            assert!(a_address != b_address, "A pool needs two different tokens.");
+            // Mint the pool's position badge.
+            let position_badge = ResourceBuilder::new_fungible()
+                .divisibility(DIVISIBILITY_NONE)
+                .metadata("name", "KrulkSwap concentrated liquidity position badge")
+                .initial_supply(1);
+            // Create a definition for the position NFTs, the pool keeps their data up to date.
+            let position_token = ResourceBuilder::new_non_fungible(NonFungibleIdType::UUID)
+                .metadata("name", "KrulkSwap concentrated liquidity position")
+                .metadata("symbol", "KSPOS")
+                .mintable(rule!(require(position_badge.resource_address())), LOCKED)
+                .burnable(rule!(require(position_badge.resource_address())), LOCKED)
+                .updateable_non_fungible_data(rule!(require(position_badge.resource_address())), LOCKED)
+                .no_initial_supply();
+
+            Self {
+                a_vault: Vault::new(a_address),
+                b_vault: Vault::new(b_address),
+                position_badge: Vault::with_bucket(position_badge),
+                position_token,
+                state: ConcentratedLiquidity::new(price, fee, tick_spacing)
+            }
+            .instantiate()
+            .globalize()
+        }
+
+        // Add liquidity within the price range from lower_tick to upper_tick.
+        // The ticks of a price are about ln(price) / ln(1.0001), and tick 0 is a price of 1.
+        // Only the amounts matching the current price are taken: above the range only token A is needed,
+        // below it only token B.
+        // Returns a position NFT, and the remainders of both tokens.
+        pub fn add_liquidity(&mut self, mut a: Bucket, mut b: Bucket, lower_tick: i32, upper_tick: i32) -> (Bucket, Bucket, Bucket) {
+            assert!(
+                a.resource_address() == self.a_vault.resource_address()
+                    && b.resource_address() == self.b_vault.resource_address(),
+                "These are not the tokens of this pool."
+            );
+            // Calculate how much liquidity the tokens can provide, and add it to the range.
+            let liquidity = self.state.liquidity_for_amounts(lower_tick, upper_tick, a.amount(), b.amount());
+            assert!(liquidity.is_positive(), "Not enough tokens to add liquidity to this range.");
+            let (a_amount, b_amount) = self.state.add_liquidity(lower_tick, upper_tick, liquidity);
+
+            // Put the tokens in the pool.
+            self.a_vault.put(a.take(a_amount));
+            self.b_vault.put(b.take(b_amount));
+
+            // Only the fees earned from now on belong to the position.
+            let (fee_growth_inside_a, fee_growth_inside_b) = self.state.fee_growth_inside(lower_tick, upper_tick);
+            let position = Position {
+                lower_tick,
+                upper_tick,
+                liquidity,
+                fee_growth_inside_a,
+                fee_growth_inside_b,
+                uncollected_fees_a: Decimal::zero(),
+                uncollected_fees_b: Decimal::zero()
+            };
+            let position_token = self.position_token;
+            let position = self.position_badge.authorize(|| {
+                borrow_resource_manager!(position_token).mint_non_fungible(&NonFungibleId::random(), position)
+            });
+            (position, a, b)
+        }
+
+        // Remove all the liquidity of a position, burning the NFT.
+        // Returns both tokens, including the uncollected fees.
+        pub fn remove_liquidity(&mut self, position: Bucket) -> (Bucket, Bucket) {
+            let (_, mut data) = self.position_data(&position);
+            // The fees have to be updated before the ticks of the position can be removed.
+            data.update_fees(&self.state);
+            let (a_amount, b_amount) = self.state.remove_liquidity(data.lower_tick, data.upper_tick, data.liquidity);
+
+            // Burn the position NFT and take the tokens out of the pool.
+            self.position_badge.authorize(|| position.burn());
+            (
+                self.a_vault.take(a_amount + data.uncollected_fees_a),
+                self.b_vault.take(b_amount + data.uncollected_fees_b)
+            )
+        }
+
+        // Collect the fees earned by a position.
+        // Returns the position NFT, and the fees in both tokens.
+        pub fn collect_fees(&mut self, position: Bucket) -> (Bucket, Bucket, Bucket) {
+            let (id, mut data) = self.position_data(&position);
+            data.update_fees(&self.state);
+            let fees_a = self.a_vault.take(data.uncollected_fees_a);
+            let fees_b = self.b_vault.take(data.uncollected_fees_b);
+            data.uncollected_fees_a = Decimal::zero();
+            data.uncollected_fees_b = Decimal::zero();
+
+            // Record the collection in the NFT.
+            let position_token = self.position_token;
+            self.position_badge.authorize(|| {
+                borrow_resource_manager!(position_token).update_non_fungible_data(&id, data)
+            });
+            (position, fees_a, fees_b)
+        }
+
+        // Swap one token of the pool for the other.
+        // The swap crosses into the next price range whenever the liquidity of a range is used up.
+        // Fails if less than min_out of the other token would be given back.
+        // Returns the other token, and what is left of the input when all the liquidity in its direction was used.
+        pub fn swap(&mut self, mut input: Bucket, min_out: Decimal) -> (Bucket, Bucket) {
+            let a_to_b = input.resource_address() == self.a_vault.resource_address();
+            assert!(
+                a_to_b || input.resource_address() == self.b_vault.resource_address(),
+                "This asset is not in this pool."
+            );
+            assert!(input.amount() > Decimal::zero(), "Cannot swap nothing.");
+
+            let (amount_used, amount_to_give) = self.state.swap(a_to_b, input.amount());
+            assert!(
+                amount_to_give >= min_out,
+                "Slippage exceeded: would receive {} but the minimum is {}",
+                amount_to_give,
+                min_out
+            );
+
+            // Put the used input in the pool, including the fee, and give back the output.
+            let output = if a_to_b {
+                self.a_vault.put(input.take(amount_used));
+                self.b_vault.take(amount_to_give)
+            } else {
+                self.b_vault.put(input.take(amount_used));
+                self.a_vault.take(amount_to_give)
+            };
+            (output, input)
+        }
+
+        // Get the tokens and the uncollected fees of a position at the current price,
+        // as (amount of A, amount of B, fees in A, fees in B).
+        pub fn position_value(&self, id: NonFungibleId) -> (Decimal, Decimal, Decimal, Decimal) {
+            let mut data: Position = borrow_resource_manager!(self.position_token).get_non_fungible_data(&id);
+            data.update_fees(&self.state);
+            let (a_amount, b_amount) = self.state.amounts(data.lower_tick, data.upper_tick, data.liquidity);
+            (a_amount, b_amount, data.uncollected_fees_a, data.uncollected_fees_b)
+        }
+
+        // Get the current price, the amount of token B one token A is worth.
+        pub fn price(&self) -> Decimal {
+            self.state.price()
+        }
+
+        pub fn current_tick(&self) -> i32 {
+            self.state.current_tick
+        }
+
+        // Get the liquidity of all the positions in range of the current price.
+        pub fn liquidity(&self) -> Decimal {
+            self.state.liquidity
+        }
+
+        pub fn position_token(&self) -> ResourceAddress {
+            self.position_token
+        }
+
+        // Check that a bucket holds a single position NFT of this pool, and get its data.
+        fn position_data(&self, position: &Bucket) -> (NonFungibleId, Position) {
+            assert!(
+                position.resource_address() == self.position_token,
+                "This is not a position of this pool."
+            );
+            let position = position.non_fungible::<Position>();
+            (position.id(), position.data())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::Rng;
+
+    // A pool without a ledger, keeping its balances and positions next to the state.
+    struct TestPool {
+        state: ConcentratedLiquidity,
+        a: Decimal,
+        b: Decimal,
+        positions: Vec<Position>,
+    }
+
+    impl TestPool {
+        fn new(price: Decimal, fee: Decimal) -> Self {
+            Self {
+                state: ConcentratedLiquidity::new(price, fee, 10),
+                a: Decimal::zero(),
+                b: Decimal::zero(),
+                positions: Vec::new(),
+            }
+        }
+
+        fn add_liquidity(&mut self, a: Decimal, b: Decimal, lower_tick: i32, upper_tick: i32) -> (Decimal, Decimal) {
+            let liquidity = self.state.liquidity_for_amounts(lower_tick, upper_tick, a, b);
+            let (a_amount, b_amount) = self.state.add_liquidity(lower_tick, upper_tick, liquidity);
+            assert!(a_amount <= a && b_amount <= b, "deposit of {} {} for {} {}", a_amount, b_amount, a, b);
+            self.a += a_amount;
+            self.b += b_amount;
+            let (fee_growth_inside_a, fee_growth_inside_b) = self.state.fee_growth_inside(lower_tick, upper_tick);
+            self.positions.push(Position {
+                lower_tick,
+                upper_tick,
+                liquidity,
+                fee_growth_inside_a,
+                fee_growth_inside_b,
+                uncollected_fees_a: Decimal::zero(),
+                uncollected_fees_b: Decimal::zero(),
+            });
+            (a_amount, b_amount)
+        }
+
+        fn remove_liquidity(&mut self, index: usize) -> (Decimal, Decimal) {
+            let mut position = self.positions.remove(index);
+            position.update_fees(&self.state);
+            let (a_amount, b_amount) = self.state.remove_liquidity(position.lower_tick, position.upper_tick, position.liquidity);
+            let (a, b) = (a_amount + position.uncollected_fees_a, b_amount + position.uncollected_fees_b);
+            self.a -= a;
+            self.b -= b;
+            (a, b)
+        }
+
+        fn swap(&mut self, a_to_b: bool, amount: Decimal) -> (Decimal, Decimal) {
+            let (used, output) = self.state.swap(a_to_b, amount);
+            assert!(used <= amount);
+            if a_to_b {
+                self.a += used;
+                self.b -= output;
+            } else {
+                self.b += used;
+                self.a -= output;
+            }
+            (used, output)
+        }
+
+        // The pool can always pay out every position with its fees.
+        fn assert_solvent(&self) {
+            let (mut a, mut b) = (Decimal::zero(), Decimal::zero());
+            for position in &self.positions {
+                let mut position = Position { ..*position };
+                position.update_fees(&self.state);
+                let (a_amount, b_amount) = self.state.amounts(position.lower_tick, position.upper_tick, position.liquidity);
+                a += a_amount + position.uncollected_fees_a;
+                b += b_amount + position.uncollected_fees_b;
+            }
+            assert!(a <= self.a, "owes {} A but has {}", a, self.a);
+            assert!(b <= self.b, "owes {} B but has {}", b, self.b);
+        }
+    }
+
+    #[test]
+    fn test_sqrt() {
+        assert_eq!(sqrt(dec!("4")), dec!("2"));
+        assert_eq!(sqrt(dec!("2")), dec!("1.414213562373095048"));
+        assert_eq!(sqrt(dec!("0.0001")), dec!("0.01"));
+    }
+
+    #[test]
+    fn test_ticks() {
+        assert_eq!(sqrt_price_at_tick(0), Decimal::one());
+        // Rounded down by the smallest amount.
+        assert_eq!(sqrt_price_at_tick(2), dec!("1.000099999999999999"));
+        // 1.0001^6932 is just above 2.
+        assert!(sqrt_price_at_tick(6931) < sqrt(dec!("2")));
+        assert!(sqrt_price_at_tick(6932) > sqrt(dec!("2")));
+        for tick in [MIN_TICK, -123_457, -1, 0, 1, 10, 6932, 199_999, MAX_TICK] {
+            assert_eq!(tick_at_sqrt_price(sqrt_price_at_tick(tick)), tick);
+            if tick < MAX_TICK {
+                assert_eq!(tick_at_sqrt_price(sqrt_price_at_tick(tick + 1) - dec!("0.000000000000000001")), tick);
+            }
+        }
+    }
+
+    #[test]
+    fn test_add_and_remove_liquidity() {
+        let mut pool = TestPool::new(Decimal::one(), dec!("0.0005"));
+        // In range both tokens are needed.
+        let (a, b) = pool.add_liquidity(dec!("1000"), dec!("1000"), -100, 100);
+        assert!(a > dec!("999") && b > dec!("999"));
+        // Above the current price only token A is needed, below it only token B.
+        assert_eq!(pool.add_liquidity(dec!("1000"), dec!("1000"), 100, 200).1, Decimal::zero());
+        assert_eq!(pool.add_liquidity(dec!("1000"), dec!("1000"), -200, -100).0, Decimal::zero());
+        assert_eq!(pool.state.liquidity, pool.positions[0].liquidity);
+
+        // The narrow range needs far less capital than a full range pool, which has a liquidity of 1000 here.
+        assert!(pool.positions[0].liquidity > dec!("200000"));
+
+        let (a_back, b_back) = pool.remove_liquidity(0);
+        assert!(a_back <= a && a - a_back < dec!("0.000000000001"));
+        assert!(b_back <= b && b - b_back < dec!("0.000000000001"));
+        assert!(pool.state.liquidity.is_zero());
+        pool.assert_solvent();
+    }
+
+    #[test]
+    fn test_swap_crosses_ticks() {
+        let mut pool = TestPool::new(Decimal::one(), dec!("0.0005"));
+        pool.add_liquidity(dec!("1000"), dec!("1000"), -100, 100);
+        pool.add_liquidity(dec!("1000"), Decimal::zero(), 100, 300);
+        let narrow = pool.state.liquidity;
+
+        // A small swap stays in the first range, at almost the price of 1.
+        let (_, output) = pool.swap(false, dec!("10"));
+        assert!(output > dec!("9.98") && output < dec!("10"));
+        assert_eq!(pool.state.current_tick, tick_at_sqrt_price(pool.state.sqrt_price));
+
+        // Buying all the A of the first range moves the price into the second one.
+        let (used, output) = pool.swap(false, dec!("1500"));
+        assert_eq!(used, dec!("1500"));
+        assert!(pool.state.current_tick >= 100 && pool.state.current_tick < 300);
+        assert_eq!(pool.state.liquidity, pool.positions[1].liquidity);
+        assert!(pool.state.liquidity != narrow);
+        assert!(output > dec!("1000") && output < dec!("1500"));
+
+        // Both positions earned fees in B, and none in A.
+        for position in &mut pool.positions {
+            position.update_fees(&pool.state);
+            assert!(position.uncollected_fees_b.is_positive());
+            assert!(position.uncollected_fees_a.is_zero());
+        }
+        pool.assert_solvent();
+
+        // Selling A crosses back into the first range.
+        pool.swap(true, dec!("1000"));
+        assert!(pool.state.current_tick < 100);
+        assert_eq!(pool.state.liquidity, narrow);
+        pool.assert_solvent();
+    }
+
+    #[test]
+    fn test_swap_runs_out_of_liquidity() {
+        let mut pool = TestPool::new(Decimal::one(), dec!("0.003"));
+        pool.add_liquidity(dec!("10"), dec!("10"), -100, 100);
+        let (used, output) = pool.swap(true, dec!("1000"));
+        // Only what the range could take was used, and all of its B was given.
+        assert!(used < dec!("11"));
+        assert!(output > dec!("9.99"));
+        assert!(pool.state.liquidity.is_zero());
+        assert_eq!(pool.state.current_tick, -101);
+        pool.assert_solvent();
+        // Nothing more can be bought.
+        assert_eq!(pool.swap(true, dec!("1")), (Decimal::zero(), Decimal::zero()));
+        // Swapping back enters the range again.
+        pool.swap(false, dec!("1"));
+        assert!(pool.state.liquidity.is_positive());
+        pool.assert_solvent();
+    }
+
+    #[test]
+    fn test_random_operations_stay_solvent() {
+        let mut rng = Rng(16);
+        let mut pool = TestPool::new(dec!("1.5"), dec!("0.001"));
+        for _ in 0..300 {
+            match rng.next() % 4 {
+                0 => {
+                    let lower_tick = (rng.next() % 400) as i32 * 10 - 2000 + 4050;
+                    let upper_tick = lower_tick + 10 + (rng.next() % 100) as i32 * 10;
+                    let (a, b) = (rng.amount(1_000), rng.amount(1_000));
+                    if pool.state.liquidity_for_amounts(lower_tick, upper_tick, a, b).is_positive() {
+                        pool.add_liquidity(a, b, lower_tick, upper_tick);
+                    }
+                }
+                1 if !pool.positions.is_empty() => {
+                    let index = (rng.next() as usize) % pool.positions.len();
+                    pool.remove_liquidity(index);
+                }
+                _ => {
+                    let a_to_b = rng.next() % 2 == 0;
+                    pool.swap(a_to_b, rng.amount(500));
+                }
+            }
+            pool.assert_solvent();
+            assert!(!pool.a.is_negative() && !pool.b.is_negative());
+        }
+        // Everyone can leave.
+        while !pool.positions.is_empty() {
+            pool.remove_liquidity(0);
+            assert!(!pool.a.is_negative() && !pool.b.is_negative());
+        }
+        assert!(pool.state.liquidity.is_zero());
+        assert!(pool.state.ticks.is_empty());
+    }
+}
@@ -2,6 +2,7 @@ use scrypto::prelude::*;
 use std::collections::HashMap;
 use crate::util::*;
 use crate::pool::*;
+use crate::router::*;
 
 // Main blueprint
 blueprint! {
@@ -9,8 +10,8 @@ blueprint! {
         // Hashmap stores all the PoolComponent instances
         // that are created in the exchange by their 2 tokens' addresses.
         pools: HashMap<(ResourceAddress, ResourceAddress), PoolComponent>,
-        // Another hashmap stores the PoolComponent instances by their LP token's resource address.
-        pools_lp: HashMap<ResourceAddress, PoolComponent>
+        // Another hashmap stores the keys of the pools by their LP token's resource address.
+        pools_lp: HashMap<ResourceAddress, (ResourceAddress, ResourceAddress)>
     }
 
     impl Exchange {
@@ -25,26 +26,30 @@ blueprint! {
         }
 
         // Adds a new pool to the exchange by instantiating a new PoolComponent.
+        // The fee is taken from every swap in the pool, e.g. 0.003 for 0.3%.
         // Returns a Bucket of LP tokens that represents the pool.
-        pub fn add_pool(&mut self, mut a: Bucket, mut b: Bucket) -> Bucket {
+        pub fn add_pool(&mut self, mut a: Bucket, mut b: Bucket, fee: Decimal) -> Bucket {
             // Sort the two buckets, so that the key for a tokenpair will always be the same.
             (a, b) = bucket_sort(a, b);
             // Check if the pool already exists.
             assert!(
-                self.pool_exists(a.resource_address(), b.resource_address()),
+                !self.pool_exists(a.resource_address(), b.resource_address()),
                 "This pool already exists"
             );
             // Create the key for the pool by combining the resource addresses.
             let pool_key = (a.resource_address(), b.resource_address());
             // Instantiate a new PoolComponent.
-            let (pool, lp_tokens) = PoolComponent::new_pool(a, b);
+            let (pool, lp_tokens) = PoolComponent::new_pool(a, b, fee);
             // Insert the PoolComponent into the hashmap with the pool key.
             self.pools.insert(pool_key, pool);
+            // Remember the pool key of the LP token, to find the pool when taking liquidity.
+            self.pools_lp.insert(lp_tokens.resource_address(), pool_key);
             lp_tokens
         }
 
         // Add some liquidity to a pool.
-        pub fn add_liquidity(&self, mut a: Bucket, mut b: Bucket) -> Bucket {
+        // Returns the LP tokens, and what is left of the tokens that didn't match the ratio of the pool.
+        pub fn add_liquidity(&self, mut a: Bucket, mut b: Bucket) -> (Bucket, Bucket, Bucket) {
             // Sort the two buckets, so that the key for a tokenpair will always be the same.
             (a, b) = bucket_sort(a, b);
             // Create the key for the pool by combining the resource addresses.
@@ -52,7 +57,7 @@ blueprint! {
             // Get the PoolComponent from the hashmap.
             let pool = self.pools.get(&pool_key).unwrap();
             // Pass the buckets off to the PoolComponent's add_liquidity
-            // method and return the LP tokens that come back.
+            // method and return the LP tokens and remainders that come back.
             pool.add_liquidity(a, b)
         }
 
@@ -61,34 +66,82 @@ blueprint! {
         pub fn take_liquidity(&self, lp: Bucket) -> (Bucket, Bucket) {
             // Check if the pool even exists.
             assert!(
-                !self.pool_lp_exists(lp.resource_address()),
+                self.pool_lp_exists(lp.resource_address()),
                 "This pool doesn't exist yet"
             );
-            // Create the pool key from the resource address of the lp token.
-            let pool_key = lp.resource_address();
+            // Get the pool key from the resource address of the lp token.
+            let pool_key = self.pools_lp[&lp.resource_address()];
             // Get the pool from the hashmap using the pool key.
-            let pool = self.pools_lp.get(&pool_key).unwrap();
+            let pool = self.pools.get(&pool_key).unwrap();
             // Pass the buckets off to the PoolComponent's take_liquidity
             // method and return the tokens that come back.
             pool.take_liquidity(lp)
         }
 
         // Swap a bucket of token A into a bucket of the target resource.
+        // The swap takes the route through up to MAX_HOPS pools that gives the most target tokens,
+        // so it also works for token pairs without a pool of their own.
+        // Fails if less than min_out of the target token would be received.
         // Returns one bucket of the target token.
-        pub fn swap(&self, input: Bucket, target_resource: ResourceAddress) -> Bucket {
+        pub fn swap(&self, input: Bucket, target_resource: ResourceAddress, min_out: Decimal) -> Bucket {
+            // Find the best route, this fails if there is none.
+            let (_, path) = self.quote(input.resource_address(), input.amount(), target_resource);
+            let mut output = input;
+            // Swap through every pool along the route.
+            for hop in path.windows(2) {
+                // Create the pool key from the sorted resource addresses of both tokens of the hop.
+                let pool_key = address_sort(hop[0], hop[1]);
+                // Get the PoolComponent from the pool key.
+                let pool = self.pools.get(&pool_key).unwrap();
+                // Pass the bucket off to the PoolComponent's swap method,
+                // only the output of the last hop is checked against min_out.
+                output = pool.swap(output, hop[1], Decimal::zero());
+            }
+            assert!(
+                output.amount() >= min_out,
+                "Slippage exceeded: would receive {} but the minimum is {}",
+                output.amount(),
+                min_out
+            );
+            output
+        }
+
+        // Get the amount of the target resource a swap of input_amount would give, without swapping.
+        // Returns the expected output and the route the swap would take,
+        // starting with the input resource and ending with the target resource.
+        pub fn quote(
+            &self,
+            input_resource: ResourceAddress,
+            input_amount: Decimal,
+            target_resource: ResourceAddress
+        ) -> (Decimal, Vec<ResourceAddress>) {
+            assert!(
+                input_resource != target_resource,
+                "Cannot swap a token for itself."
+            );
+            best_route(&self.pool_states(), input_resource, input_amount, target_resource, MAX_HOPS)
+                .expect("There is no route between these tokens")
+        }
+
+        // Get the time-weighted average price of a token in the quote token,
+        // from the start of from_epoch to the start of to_epoch.
+        // The pool must have existed for the whole window, and the window cannot end in the future.
+        // This is the price other components should use, as the current price of a pool is easy to move.
+        pub fn time_weighted_average_price(
+            &self,
+            resource: ResourceAddress,
+            quote_resource: ResourceAddress,
+            from_epoch: u64,
+            to_epoch: u64
+        ) -> Decimal {
             // Check if the pool even exists for this token pair.
             assert!(
-                !self.pool_exists(input.resource_address(), target_resource),
+                self.pool_exists(resource, quote_resource),
                 "This pool doesn't exist yet"
             );
-            // Create the pool key from the resource address of
-            // the input bucket and the target resource address.
-            let pool_key = (input.resource_address(), target_resource);
-            // Get the PoolComponent from the pool key.
-            let pool = self.pools.get(&pool_key).unwrap();
-            // Pass the buckets off to the PoolComponent's swap
-            // method and return the tokens that come back.
-            pool.swap(input, target_resource)
+            // Get the PoolComponent from the sorted pool key.
+            let pool = self.pools.get(&address_sort(resource, quote_resource)).unwrap();
+            pool.time_weighted_average_price(resource, from_epoch, to_epoch)
         }
 
         // Check if a pool exists for a token pair.
@@ -104,5 +157,19 @@ blueprint! {
             // Return the bool value.
             self.pools_lp.contains_key(&lp)
         }
+
+        // Take a snapshot of the balances and fees of all pools, to find routes through them.
+        fn pool_states(&self) -> Vec<PoolState> {
+            self.pools
+                .iter()
+                .map(|(&(a, b), pool)| PoolState {
+                    a,
+                    a_balance: pool.balance(a),
+                    b,
+                    b_balance: pool.balance(b),
+                    fee: pool.fee()
+                })
+                .collect()
+        }
     }
 }
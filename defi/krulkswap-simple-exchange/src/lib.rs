@@ -1,3 +1,6 @@
 mod krulkswap;
 mod util;
 mod pool;
+mod oracle;
+mod router;
+mod concentrated_pool;
@@ -0,0 +1,80 @@
+use scrypto::prelude::*;
+use std::collections::BTreeMap;
+
+// A price accumulator keeps the sum of the price of a token over all epochs since the pool was created,
+// observed at every epoch in which the pool was used.
+// The time-weighted average price (TWAP) between two epochs is then the difference of the sums divided by the epochs between them.
+// A pool's price can only be pushed into the sum by keeping it there across epochs,
+// which makes the TWAP expensive to manipulate compared to the current price.
+
+// Add the price held since the last observation to the accumulator, observing it at the current epoch.
+// This must be called before the balances of the pool change, as the old price is the one that was held.
+pub fn observe_price(observations: &mut BTreeMap<u64, Decimal>, price: Decimal, epoch: u64) {
+    let (&last_epoch, &last_cumulative) = observations.iter().next_back().unwrap();
+    if epoch > last_epoch {
+        observations.insert(epoch, last_cumulative + price * Decimal::from(epoch - last_epoch));
+    }
+}
+
+// Get the value of the accumulator at an epoch.
+// Between two observations the price was the same, so the value is interpolated,
+// after the last observation the current price is still being held.
+pub fn cumulative_price(observations: &BTreeMap<u64, Decimal>, current_price: Decimal, epoch: u64) -> Decimal {
+    let (&before_epoch, &before) = observations
+        .range(..=epoch)
+        .next_back()
+        .expect("There is no price for epochs before the pool was created");
+    match observations.range(epoch + 1..).next() {
+        Some((&after_epoch, &after)) => {
+            before + (after - before) * Decimal::from(epoch - before_epoch) / Decimal::from(after_epoch - before_epoch)
+        }
+        None => before + current_price * Decimal::from(epoch - before_epoch),
+    }
+}
+
+// Get the time-weighted average price between two epochs.
+pub fn time_weighted_average_price(
+    observations: &BTreeMap<u64, Decimal>,
+    current_price: Decimal,
+    from_epoch: u64,
+    to_epoch: u64
+) -> Decimal {
+    assert!(from_epoch < to_epoch, "The epoch window must end after it starts");
+    let from = cumulative_price(observations, current_price, from_epoch);
+    let to = cumulative_price(observations, current_price, to_epoch);
+    (to - from) / Decimal::from(to_epoch - from_epoch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_twap() {
+        // The pool was created at epoch 10 with a price of 2.
+        let mut observations = BTreeMap::new();
+        observations.insert(10, Decimal::zero());
+        // Several swaps in epoch 10 only count with the price they left at the end of the epoch.
+        observe_price(&mut observations, dec!("2"), 10);
+        observe_price(&mut observations, dec!("50"), 10);
+        assert_eq!(observations.len(), 1);
+        // The price was moved to 4 in epoch 10, and to 1 in epoch 14.
+        observe_price(&mut observations, dec!("4"), 14);
+        assert_eq!(observations[&14], dec!("16"));
+
+        assert_eq!(time_weighted_average_price(&observations, dec!("1"), 10, 14), dec!("4"));
+        assert_eq!(time_weighted_average_price(&observations, dec!("1"), 11, 13), dec!("4"));
+        // The current price is used after the last observation.
+        assert_eq!(time_weighted_average_price(&observations, dec!("1"), 14, 20), dec!("1"));
+        // 4 epochs at 4 and 4 at 1.
+        assert_eq!(time_weighted_average_price(&observations, dec!("1"), 10, 18), dec!("2.5"));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_twap_before_creation() {
+        let mut observations = BTreeMap::new();
+        observations.insert(10, Decimal::zero());
+        time_weighted_average_price(&observations, dec!("1"), 5, 12);
+    }
+}
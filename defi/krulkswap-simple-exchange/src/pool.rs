@@ -1,5 +1,52 @@
 use scrypto::prelude::*;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use crate::oracle::*;
+
+// The pool math is kept outside the blueprint, so it can be tested without a ledger.
+// All the divisions round down, which always leaves the rounding dust in the pool.
+
+// Calculate the amount of output tokens to give for an amount of input tokens.
+// The fee is taken from the input and stays in the pool, so the LP-tokens grow in value.
+// This follows from (a + input * (1 - fee)) * (b - output) = a * b
+pub fn swap_output(input_amount: Decimal, input_balance: Decimal, output_balance: Decimal, fee: Decimal) -> Decimal {
+    let input_after_fee = input_amount * (Decimal::one() - fee);
+    output_balance * input_after_fee / (input_balance + input_after_fee)
+}
+
+// Calculate how much of the offered tokens can be added to the pool without changing its price.
+// The token that is offered in excess is only partially taken.
+pub fn liquidity_deposit(a_amount: Decimal, b_amount: Decimal, a_balance: Decimal, b_balance: Decimal) -> (Decimal, Decimal) {
+    // An empty pool takes everything, which sets a new price.
+    if a_balance.is_zero() || b_balance.is_zero() {
+        return (a_amount, b_amount);
+    }
+    let ratio = std::cmp::min(a_amount / a_balance, b_amount / b_balance);
+    (
+        std::cmp::min(a_amount, a_balance * ratio),
+        std::cmp::min(b_amount, b_balance * ratio)
+    )
+}
+
+// Calculate the amount of LP-tokens to mint for a deposit.
+// This is the smallest share of the pool the deposit represents, so existing LP-tokens never lose value.
+pub fn liquidity_to_mint(a_deposit: Decimal, b_deposit: Decimal, a_balance: Decimal, b_balance: Decimal, lp_total_supply: Decimal) -> Decimal {
+    // let's define the initial amount of LP-tokens arbitrarily as the sum of the initial amounts.
+    if lp_total_supply.is_zero() || a_balance.is_zero() || b_balance.is_zero() {
+        return a_deposit + b_deposit;
+    }
+    std::cmp::min(
+        a_deposit * lp_total_supply / a_balance,
+        b_deposit * lp_total_supply / b_balance
+    )
+}
+
+// Calculate the amounts of tokens to give back for some LP-tokens.
+pub fn liquidity_to_return(lp_amount: Decimal, a_balance: Decimal, b_balance: Decimal, lp_total_supply: Decimal) -> (Decimal, Decimal) {
+    (
+        a_balance * lp_amount / lp_total_supply,
+        b_balance * lp_amount / lp_total_supply
+    )
+}
 
 // Satellite blueprint for the Exchange main component blueprint.
 blueprint! {
@@ -14,6 +61,12 @@ blueprint! {
         lp_initial_supply: Decimal,
         // Current total supply of the LP tokens.
         lp_total_supply: Decimal,
+        // Fee taken from the input of every swap, e.g. 0.003 for 0.3%.
+        // It stays in the pool, which makes the k constant grow with every swap.
+        fee: Decimal,
+        // Price accumulators of both tokens, observed by epoch, for the time-weighted average price.
+        // The price of a token is the amount of the other token one of it is worth.
+        price_observations: HashMap<ResourceAddress, BTreeMap<u64, Decimal>>,
         // Special sauce K constant of CFMM. (explained below)
         k_constant: Decimal
 
@@ -33,7 +86,11 @@ blueprint! {
     impl Pool {
         // Instantiate a PoolComponent
         // Returns the instance and a bucket of LP-tokens.
-        pub fn new_pool(a: Bucket, b: Bucket) -> (PoolComponent, Bucket) {
+        pub fn new_pool(a: Bucket, b: Bucket, fee: Decimal) -> (PoolComponent, Bucket) {
+            assert!(
+                fee >= Decimal::zero() && fee < Decimal::one(),
+                "The fee must be at least 0 and less than 1"
+            );
             let a_address = a.resource_address();
             let b_address = b.resource_address();
             // Mint the pool's LP minting badge.
@@ -44,16 +101,23 @@ blueprint! {
             let lp_def = ResourceBuilder::new_fungible()
                 .metadata("name", "KrulkSwap ".to_owned() + " LP token")
                 .metadata("symbol", "LP")
-                // Require the internal mint badge to mint more LP tokens.
+                // Require the internal mint badge to mint more LP tokens, or to burn them.
                 .mintable(rule!(require(mint_badge.resource_address())), LOCKED)
+                .burnable(rule!(require(mint_badge.resource_address())), LOCKED)
                 .no_initial_supply();
 
             // We choose to define k as the product of the two token amounts here.
             let k = a.amount() * b.amount();
-            // let's define the initial amount of LP-tokens arbitrarily as the sum of the intitial amounts.
-            let lp_total_supply = a.amount() + b.amount();
+            let lp_total_supply = liquidity_to_mint(a.amount(), b.amount(), Decimal::zero(), Decimal::zero(), Decimal::zero());
             // Mint LP-tokens using the lp_def definition.
-            let lp_tokens = borrow_resource_manager!(lp_def).mint(lp_total_supply);
+            let lp_tokens = mint_badge.authorize(|| borrow_resource_manager!(lp_def).mint(lp_total_supply));
+
+            // Start the price accumulators at the current epoch.
+            let mut observations = BTreeMap::new();
+            observations.insert(Runtime::current_epoch(), Decimal::zero());
+            let mut price_observations = HashMap::new();
+            price_observations.insert(a_address, observations.clone());
+            price_observations.insert(b_address, observations);
 
             // Create a hashmap for the vaults.
             let mut vaults = HashMap::new();
@@ -66,8 +130,10 @@ blueprint! {
                 vaults: vaults,
                 lp_badge: Vault::with_bucket(mint_badge),
                 lp_token: lp_def,
-                lp_initial_supply: k,
+                lp_initial_supply: lp_total_supply,
                 lp_total_supply: lp_total_supply,
+                fee: fee,
+                price_observations: price_observations,
                 k_constant: k
             }
             // Instantiate the instance. and return the instance together with the LP-tokens.
@@ -75,84 +141,102 @@ blueprint! {
         }
 
         // Add some liquidity to this pool.
-        // Returns a Bucket of LP tokens for this pool.
-        pub fn add_liquidity(&mut self, a: Bucket, b: Bucket) -> Bucket {
+        // Only the amounts matching the current ratio of the pool are taken,
+        // otherwise the "price" would change.
+        // Returns a Bucket of LP tokens for this pool, and the remainders of both tokens.
+        pub fn add_liquidity(&mut self, mut a: Bucket, mut b: Bucket) -> (Bucket, Bucket, Bucket) {
             let a_vault_balance = self.vaults[&a.resource_address()].amount();
             let b_vault_balance = self.vaults[&b.resource_address()].amount();
 
+            // Add the price held until now to the accumulators, before it changes.
+            self.observe_prices();
 
-            // Calculate the ratio between the amount of given tokens and the tokens in the pool.
-            // These must be the same when adding liquidity, or the "price" will change.
-            let a_ratio = a.amount() / a_vault_balance;
-            let b_ratio = b.amount() / b_vault_balance;
-
-            // Check if the ratios are equal.
-            assert!(
-                a_ratio == b_ratio,
-                "Input token ratio is incorrect"
-            );
+            // Calculate how much of each token can be added.
+            let (a_deposit, b_deposit) = liquidity_deposit(a.amount(), b.amount(), a_vault_balance, b_vault_balance);
 
             // Calculate the amount of LP-tokens to mint in return.
             // This should represent your share of the pool.
-            let amount_of_lp = a_ratio * (a_vault_balance + b_vault_balance);
+            let amount_of_lp = liquidity_to_mint(a_deposit, b_deposit, a_vault_balance, b_vault_balance, self.lp_total_supply);
 
             // Put the tokens in the pool.
-            self.put(a);
-            self.put(b);
+            self.put(a.take(a_deposit));
+            self.put(b.take(b_deposit));
 
             // Update the total supply of LP tokens.
             self.lp_total_supply += amount_of_lp;
             // Update the k-value of the pool.
-            self.k_constant = a_vault_balance * b_vault_balance;
-            // Mint the LP-tokens and return them.
-            borrow_resource_manager!(self.lp_token).mint(amount_of_lp)
+            self.update_k_constant();
+            // Mint the LP-tokens and return them together with the remainders.
+            let lp_token = self.lp_token;
+            let lp_tokens = self.lp_badge.authorize(|| borrow_resource_manager!(lp_token).mint(amount_of_lp));
+            (lp_tokens, a, b)
         }
 
         // Redeem LP-tokens and return the liquidity.
         pub fn take_liquidity(&mut self, lp: Bucket) -> (Bucket, Bucket) {
+            assert!(
+                lp.resource_address() == self.lp_token,
+                "These are not the LP-tokens of this pool."
+            );
             // Get the ResourceAddresses of the tokens in the pool.
             let addresses = self.addresses();
             // Get the balances of the vaults in the pool.
             let a_vault_balance = self.vaults[&addresses[0]].amount();
             let b_vault_balance = self.vaults[&addresses[1]].amount();
 
+            // Add the price held until now to the accumulators, before it changes.
+            self.observe_prices();
+
             // Calculate the amount to take from each pool.
-            let factor_of_pool = lp.amount() / self.lp_total_supply;
-            let amount_of_a = factor_of_pool * a_vault_balance;
-            let amount_of_b = factor_of_pool * b_vault_balance;
+            let (amount_of_a, amount_of_b) = liquidity_to_return(lp.amount(), a_vault_balance, b_vault_balance, self.lp_total_supply);
 
             // Update the LP total supply.
             self.lp_total_supply -= lp.amount();
-            // Update the k value of the pool.
-            self.k_constant = a_vault_balance * b_vault_balance;
 
-            // Burn the LP tokens and return a tuple with the token Buckets.
-            borrow_resource_manager!(self.lp_token).burn(lp);
-            (self.take(self.vaults[&addresses[0]].resource_address(), amount_of_a),
-            self.take(self.vaults[&addresses[0]].resource_address(), amount_of_b))
+            // Burn the LP tokens and take the tokens out of the pool.
+            self.lp_badge.authorize(|| lp.burn());
+            let a = self.take(addresses[0], amount_of_a);
+            let b = self.take(addresses[1], amount_of_b);
+            // Update the k value of the pool.
+            self.update_k_constant();
+            // Return a tuple with the token Buckets.
+            (a, b)
         }
 
         // Swap one token for the target token.
+        // Fails if less than min_out target tokens would be given back.
         // Returns a bucket with the target tokens.
-        pub fn swap(&mut self, input: Bucket, target_resource: ResourceAddress) -> Bucket {
+        pub fn swap(&mut self, input: Bucket, target_resource: ResourceAddress, min_out: Decimal) -> Bucket {
+            assert!(
+                input.resource_address() != target_resource,
+                "Cannot swap a token for itself."
+            );
+            assert!(input.amount() > Decimal::zero(), "Cannot swap nothing.");
+            // Add the price held until now to the accumulators, before it changes.
+            self.observe_prices();
             // Get the balances of the vaults.
             let input_vault_balance = self.vaults[&input.resource_address()].amount();
             let output_vault_balance = self.vaults[&target_resource].amount();
-            // Calculate the new balance that A will have.
-            let new_input_amount = input_vault_balance + input.amount();
-            // Calculate the new balance token B should have.
-            // Note that we use a form of the k = a * b equation here: b = k / a
-            let new_output_vault_balance = self.k_constant / new_input_amount;
-            // To get the amount of B to give, we substract the balance of B from the old
-            // balance of B, which gives us the difference.
-            let amount_to_give = output_vault_balance - new_output_vault_balance;
-            self.take(target_resource, amount_to_give)
+            // Calculate the amount of B to give, the fee is taken from the input.
+            let amount_to_give = swap_output(input.amount(), input_vault_balance, output_vault_balance, self.fee);
+            assert!(
+                amount_to_give >= min_out,
+                "Slippage exceeded: would receive {} but the minimum is {}",
+                amount_to_give,
+                min_out
+            );
+            // Put all of the input in the pool, including the fee, and give back the output.
+            self.put(input);
+            let output = self.take(target_resource, amount_to_give);
+            // The fee makes the new k bigger than the old one.
+            self.update_k_constant();
+            output
         }
 
         // Put some tokens into the pool.
         fn put(&mut self, bucket: Bucket) {
             assert!(
-                !self.vaults.contains_key(&bucket.resource_address()),
+                self.vaults.contains_key(&bucket.resource_address()),
                 "This asset is not in this pool."
             );
             self.vaults.get_mut(&bucket.resource_address()).unwrap().put(bucket);
@@ -161,13 +245,210 @@ blueprint! {
         // Take some tokens out of the pool.
         fn take(&mut self, resource: ResourceAddress, amount: Decimal) -> Bucket {
             assert!(
-                !self.vaults.contains_key(&resource),
+                self.vaults.contains_key(&resource),
                 "This asset is not in this pool."
             );
             self.vaults.get_mut(&resource).unwrap().take(amount)
         }
+
+        // Observe the current prices at the current epoch.
+        fn observe_prices(&mut self) {
+            let epoch = Runtime::current_epoch();
+            for resource in self.addresses() {
+                let price = self.price(resource);
+                observe_price(self.price_observations.get_mut(&resource).unwrap(), price, epoch);
+            }
+        }
+
+        // Set k to the product of the current balances.
+        fn update_k_constant(&mut self) {
+            self.k_constant = self.vaults.values().fold(Decimal::one(), |k, vault| k * vault.amount());
+        }
+
         pub fn addresses(&self) -> Vec<ResourceAddress> {
             self.vaults.keys().cloned().collect::<Vec<ResourceAddress>>()
         }
+
+        pub fn balance(&self, resource: ResourceAddress) -> Decimal {
+            self.vaults[&resource].amount()
+        }
+
+        // Get the current price of a token, in the other token of the pool.
+        // This is easy to move with a big swap, use the time-weighted average price where that matters.
+        pub fn price(&self, resource: ResourceAddress) -> Decimal {
+            let balance = self.balance(resource);
+            let other = self.addresses().into_iter().find(|&address| address != resource).unwrap();
+            if balance.is_zero() {
+                return Decimal::zero();
+            }
+            self.balance(other) / balance
+        }
+
+        // Get the time-weighted average price of a token, in the other token of the pool,
+        // from the start of from_epoch to the start of to_epoch.
+        pub fn time_weighted_average_price(&self, resource: ResourceAddress, from_epoch: u64, to_epoch: u64) -> Decimal {
+            assert!(
+                to_epoch <= Runtime::current_epoch(),
+                "The epoch window cannot end in the future"
+            );
+            time_weighted_average_price(&self.price_observations[&resource], self.price(resource), from_epoch, to_epoch)
+        }
+
+        pub fn lp_token(&self) -> ResourceAddress {
+            self.lp_token
+        }
+
+        pub fn fee(&self) -> Decimal {
+            self.fee
+        }
+
+        pub fn k_constant(&self) -> Decimal {
+            self.k_constant
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::Rng;
+
+    // A pool without a ledger, using the same math as the blueprint.
+    #[derive(Clone)]
+    struct TestPool {
+        a: Decimal,
+        b: Decimal,
+        lp_total_supply: Decimal,
+        fee: Decimal,
+    }
+
+    impl TestPool {
+        fn new(a: Decimal, b: Decimal, fee: Decimal) -> Self {
+            let lp_total_supply = liquidity_to_mint(a, b, Decimal::zero(), Decimal::zero(), Decimal::zero());
+            Self { a, b, lp_total_supply, fee }
+        }
+
+        fn k(&self) -> Decimal {
+            self.a * self.b
+        }
+
+        fn swap_a(&mut self, input: Decimal) -> Decimal {
+            let output = swap_output(input, self.a, self.b, self.fee);
+            self.a += input;
+            self.b -= output;
+            output
+        }
+
+        fn swap_b(&mut self, input: Decimal) -> Decimal {
+            let output = swap_output(input, self.b, self.a, self.fee);
+            self.b += input;
+            self.a -= output;
+            output
+        }
+
+        fn add_liquidity(&mut self, a: Decimal, b: Decimal) -> Decimal {
+            let (a_deposit, b_deposit) = liquidity_deposit(a, b, self.a, self.b);
+            assert!(a_deposit <= a && b_deposit <= b);
+            let lp = liquidity_to_mint(a_deposit, b_deposit, self.a, self.b, self.lp_total_supply);
+            self.a += a_deposit;
+            self.b += b_deposit;
+            self.lp_total_supply += lp;
+            lp
+        }
+
+        fn take_liquidity(&mut self, lp: Decimal) -> (Decimal, Decimal) {
+            let (a, b) = liquidity_to_return(lp, self.a, self.b, self.lp_total_supply);
+            self.a -= a;
+            self.b -= b;
+            self.lp_total_supply -= lp;
+            (a, b)
+        }
+
+        // Neither token per LP-token decreased, so k per LP-token squared didn't either.
+        fn assert_lp_value_kept(&self, before: &TestPool) {
+            assert!(self.a * before.lp_total_supply >= before.a * self.lp_total_supply);
+            assert!(self.b * before.lp_total_supply >= before.b * self.lp_total_supply);
+        }
+    }
+
+    #[test]
+    fn test_swap_output() {
+        // Without a fee, 10 A in a 100 A / 100 B pool gives 100 - 10000 / 110 B.
+        assert_eq!(
+            swap_output(dec!("10"), dec!("100"), dec!("100"), Decimal::zero()),
+            dec!("9.090909090909090909")
+        );
+        // A fee lowers the output.
+        assert!(swap_output(dec!("10"), dec!("100"), dec!("100"), dec!("0.003")) < dec!("9.090909090909090909"));
+        // The output can never drain the pool.
+        assert!(swap_output(dec!("1000000"), dec!("1"), dec!("100"), dec!("0.003")) < dec!("100"));
+    }
+
+    #[test]
+    fn test_fee_grows_k() {
+        let mut pool = TestPool::new(dec!("1000"), dec!("1000"), dec!("0.003"));
+        let k = pool.k();
+        let output = pool.swap_a(dec!("100"));
+        pool.swap_b(output);
+        // Swapping back and forth leaves the fees in the pool.
+        assert!(pool.k() > k);
+        assert!(pool.a > dec!("1000"));
+    }
+
+    #[test]
+    fn test_add_liquidity_returns_excess() {
+        let mut pool = TestPool::new(dec!("100"), dec!("200"), dec!("0.003"));
+        let lp = pool.add_liquidity(dec!("10"), dec!("50"));
+        // Only 10 A and 20 B match the price of the pool.
+        assert_eq!(pool.a, dec!("110"));
+        assert_eq!(pool.b, dec!("220"));
+        assert_eq!(lp, dec!("30"));
+        assert_eq!(pool.take_liquidity(lp), (dec!("10"), dec!("20")));
+    }
+
+    #[test]
+    fn test_k_never_decreases_on_swaps() {
+        let mut rng = Rng(13);
+        for fee in [Decimal::zero(), dec!("0.003"), dec!("0.01")] {
+            let mut pool = TestPool::new(rng.amount(10_000), rng.amount(10_000), fee);
+            for _ in 0..1000 {
+                let k = pool.k();
+                if rng.next() % 2 == 0 {
+                    pool.swap_a(rng.amount(1_000));
+                } else {
+                    pool.swap_b(rng.amount(1_000));
+                }
+                assert!(pool.k() >= k, "k decreased from {} to {}", k, pool.k());
+                assert!(pool.a.is_positive() && pool.b.is_positive());
+            }
+        }
+    }
+
+    #[test]
+    fn test_k_never_decreases_on_liquidity_changes() {
+        let mut rng = Rng(42);
+        let mut pool = TestPool::new(rng.amount(10_000), rng.amount(10_000), dec!("0.003"));
+        for _ in 0..1000 {
+            let before = pool.clone();
+            match rng.next() % 3 {
+                0 => {
+                    // k can only grow when liquidity is added.
+                    pool.add_liquidity(rng.amount(1_000), rng.amount(1_000));
+                    assert!(pool.k() >= before.k());
+                    pool.assert_lp_value_kept(&before);
+                }
+                1 => {
+                    // Taking liquidity lowers k, but never the k of each LP-token.
+                    let lp = std::cmp::min(rng.amount(1_000), pool.lp_total_supply / Decimal::from(2));
+                    pool.take_liquidity(lp);
+                    pool.assert_lp_value_kept(&before);
+                }
+                _ => {
+                    // Swaps in between make the ratio of the pool drift.
+                    pool.swap_a(rng.amount(100));
+                    assert!(pool.k() >= before.k());
+                }
+            }
+        }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,170 @@
+use scrypto::prelude::*;
+use crate::pool::swap_output;
+
+// The maximum number of pools a single swap is routed through.
+pub const MAX_HOPS: usize = 3;
+
+// A snapshot of a pool, which is all that is needed to calculate the output of swaps through it.
+pub struct PoolState {
+    pub a: ResourceAddress,
+    pub a_balance: Decimal,
+    pub b: ResourceAddress,
+    pub b_balance: Decimal,
+    pub fee: Decimal
+}
+
+impl PoolState {
+    // Get the other token of the pool and the output of a swap, if the pool can swap the input token.
+    fn swap(&self, input: ResourceAddress, amount: Decimal) -> Option<(ResourceAddress, Decimal)> {
+        // An empty pool can't swap anything.
+        if self.a_balance.is_zero() || self.b_balance.is_zero() {
+            return None;
+        }
+        if input == self.a {
+            Some((self.b, swap_output(amount, self.a_balance, self.b_balance, self.fee)))
+        } else if input == self.b {
+            Some((self.a, swap_output(amount, self.b_balance, self.a_balance, self.fee)))
+        } else {
+            None
+        }
+    }
+}
+
+// Find the route through the pools that gives the most target tokens for an amount of input tokens.
+// A route goes through at most max_hops pools and never visits the same token twice.
+// Returns the output and the tokens along the route, starting with the input and ending with the target.
+pub fn best_route(
+    pools: &[PoolState],
+    input: ResourceAddress,
+    amount: Decimal,
+    target: ResourceAddress,
+    max_hops: usize
+) -> Option<(Decimal, Vec<ResourceAddress>)> {
+    let mut path = vec![input];
+    let mut best = None;
+    search(pools, &mut path, amount, target, max_hops, &mut best);
+    best
+}
+
+// Depth first search over every route from the last token of the path.
+fn search(
+    pools: &[PoolState],
+    path: &mut Vec<ResourceAddress>,
+    amount: Decimal,
+    target: ResourceAddress,
+    max_hops: usize,
+    best: &mut Option<(Decimal, Vec<ResourceAddress>)>
+) {
+    if path.len() > max_hops {
+        return;
+    }
+    let current = *path.last().unwrap();
+    for pool in pools {
+        let (next, output) = match pool.swap(current, amount) {
+            Some(swap) => swap,
+            None => continue
+        };
+        if path.contains(&next) {
+            continue;
+        }
+        path.push(next);
+        if next == target {
+            // On a tie, prefer the route with the fewest hops.
+            let better = match best {
+                Some((best_output, best_path)) => output > *best_output || (output == *best_output && path.len() < best_path.len()),
+                None => true
+            };
+            if better {
+                *best = Some((output, path.clone()));
+            }
+        } else {
+            search(pools, path, output, target, max_hops, best);
+        }
+        path.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(id: u8) -> ResourceAddress {
+        ResourceAddress::Normal([id; 26])
+    }
+
+    fn pool(a: u8, a_balance: &str, b: u8, b_balance: &str) -> PoolState {
+        PoolState {
+            a: token(a),
+            a_balance: Decimal::from(a_balance),
+            b: token(b),
+            b_balance: Decimal::from(b_balance),
+            fee: Decimal::zero()
+        }
+    }
+
+    #[test]
+    fn test_direct_route_beats_worse_route() {
+        let pools = vec![
+            pool(1, "1000", 3, "1000"),
+            pool(3, "1000", 2, "1000"),
+            pool(1, "1000", 2, "1000")
+        ];
+        let (output, path) = best_route(&pools, token(1), dec!("10"), token(2), MAX_HOPS).unwrap();
+        assert_eq!(path, vec![token(1), token(2)]);
+        assert_eq!(output, swap_output(dec!("10"), dec!("1000"), dec!("1000"), Decimal::zero()));
+    }
+
+    #[test]
+    fn test_two_hops_beat_thin_direct_pool() {
+        let pools = vec![
+            pool(1, "10", 2, "10"),
+            pool(1, "1000", 3, "1000"),
+            pool(3, "1000", 2, "1000")
+        ];
+        let (output, path) = best_route(&pools, token(1), dec!("10"), token(2), MAX_HOPS).unwrap();
+        assert_eq!(path, vec![token(1), token(3), token(2)]);
+        // The direct pool would only give 5.
+        assert!(output > dec!("5"));
+    }
+
+    #[test]
+    fn test_max_hops() {
+        let pools = vec![
+            pool(1, "1000", 2, "1000"),
+            pool(2, "1000", 3, "1000"),
+            pool(3, "1000", 4, "1000"),
+            pool(4, "1000", 5, "1000")
+        ];
+        assert!(best_route(&pools, token(1), dec!("10"), token(5), MAX_HOPS).is_none());
+        // The same path is found when 4 hops are allowed.
+        let (_, path) = best_route(&pools, token(1), dec!("10"), token(5), 4).unwrap();
+        assert_eq!(path, vec![token(1), token(2), token(3), token(4), token(5)]);
+    }
+
+    #[test]
+    fn test_empty_pools_are_skipped() {
+        let pools = vec![pool(1, "0", 2, "0")];
+        assert!(best_route(&pools, token(1), dec!("10"), token(2), MAX_HOPS).is_none());
+
+        let pools = vec![
+            pool(1, "1000", 2, "0"),
+            pool(1, "1000", 3, "1000"),
+            pool(3, "1000", 2, "1000")
+        ];
+        let (_, path) = best_route(&pools, token(1), dec!("10"), token(2), MAX_HOPS).unwrap();
+        assert_eq!(path, vec![token(1), token(3), token(2)]);
+    }
+
+    #[test]
+    fn test_tie_goes_to_fewer_hops() {
+        // 1000 in gives exactly 500 out either way, and the 2-hop route is found first.
+        let pools = vec![
+            pool(1, "1000", 3, "1000"),
+            pool(3, "500", 2, "1000"),
+            pool(1, "1000", 2, "1000")
+        ];
+        let (output, path) = best_route(&pools, token(1), dec!("1000"), token(2), MAX_HOPS).unwrap();
+        assert_eq!(output, dec!("500"));
+        assert_eq!(path, vec![token(1), token(2)]);
+    }
+}
@@ -17,3 +17,21 @@ pub fn bucket_sort(a: Bucket, b: Bucket) -> (Bucket, Bucket) {
         return (b, a)
     }
 }
+
+// Small deterministic pseudo random generator, so the tests are reproducible.
+#[cfg(test)]
+pub struct Rng(pub u64);
+
+#[cfg(test)]
+impl Rng {
+    pub fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        self.0 >> 33
+    }
+
+    // A random amount between 0.000001 and max, with 6 decimals.
+    pub fn amount(&mut self, max: u64) -> Decimal {
+        let micros = 1 + self.next() % (max * 1_000_000);
+        Decimal::from(micros) / Decimal::from(1_000_000)
+    }
+}
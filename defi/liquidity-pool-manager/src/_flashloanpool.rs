@@ -64,6 +64,8 @@ blueprint! {
             let access_rules = AccessRules::new()
                 .method("add_liquidity", rule!(require(admin_badge.resource_address())), AccessRule::DenyAll)
                 .method("remove_liquidity", rule!(require(admin_badge.resource_address())), AccessRule::DenyAll)
+                .method("take_loan", rule!(require(admin_badge.resource_address())), AccessRule::DenyAll)
+                .method("repay_loan", rule!(require(admin_badge.resource_address())), AccessRule::DenyAll)
                 .default(AccessRule::AllowAll, AccessRule::DenyAll);
 
             flash_loan_component.add_access_check(access_rules);
@@ -76,6 +78,10 @@ blueprint! {
             self.loan_vault.amount()
         }
 
+        pub fn resource_address(&self) -> ResourceAddress {
+            self.loan_vault.resource_address()
+        }
+
         pub fn add_liquidity(&mut self, tokens: Bucket) {
             self.loan_vault.put(tokens);
         }
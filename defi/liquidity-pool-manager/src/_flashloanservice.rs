@@ -1,5 +1,19 @@
 // use crate::poolmanager::*;
 use scrypto::prelude::*;
+use std::collections::BTreeMap;
+use crate::_flashloanpool::LoanDue;
+use crate::_weightedpool::WeightedLoanDue;
+
+// Roughly the number of epochs in a year, used to annualise the return of the pool
+const EPOCHS_PER_YEAR: u64 = 15_000;
+
+/// Cumulative flash loan figures of a resource of the pool
+#[derive(TypeId, Encode, Decode, Describe, Clone, Debug)]
+pub struct LoanStats {
+    pub loan_count: u64,
+    pub volume: Decimal,
+    pub fees_earned: Decimal,
+}
 
 // Define the functions on the Radiswap blueprint
 external_blueprint! {
@@ -14,6 +28,28 @@ external_component! {
         fn add_liquidity(&mut self, tokens: Bucket) ;
         fn remove_liquidity(&mut self, tokens: Decimal) -> Bucket;
         fn available_liquidity(&self) -> Decimal;
+        fn resource_address(&self) -> ResourceAddress;
+        fn take_loan(&mut self, loan_amount: Decimal) -> (Bucket, Bucket);
+        fn repay_loan(&mut self, loan_repayment: Bucket, loan_terms: Bucket) -> Bucket;
+    }
+}
+
+// Define the functions on the WeightedPool blueprint
+external_blueprint! {
+    WeightedPoolTarget {
+        fn instantiate_weighted(resources: Vec<ResourceAddress>, weights: Vec<Decimal>, swap_fee: Decimal, loan_fee_rate: Decimal) -> (ComponentAddress,Bucket);
+    }
+}
+
+// Define the methods on instantiated components
+external_component! {
+    WeightedPoolComponentTarget {
+        fn add_liquidity(&mut self, tokens: Vec<Bucket>) -> Decimal;
+        fn remove_liquidity(&mut self, value_removed: Decimal) -> Vec<Bucket>;
+        fn remove_liquidity_single(&mut self, value_removed: Decimal, resource_address: ResourceAddress) -> Bucket;
+        fn invariant(&self) -> Decimal;
+        fn take_loan(&mut self, resource_address: ResourceAddress, loan_amount: Decimal) -> (Bucket, Bucket);
+        fn repay_loan(&mut self, loan_repayment: Bucket, loan_terms: Bucket) -> Bucket;
     }
 }
 
@@ -29,16 +65,23 @@ external_component! {
     PoolManagerComponentTarget {
         fn add_liquidity(&mut self,tokens: Decimal, pool_amount: Decimal) -> Bucket;
         fn remove_liquidity(&mut self, lp_tokens: Bucket, pool_amount: Decimal  ) -> Decimal;
+        fn ajust_lp_per_asset_ratio(&mut self, pool_amount: Decimal);
+        fn lp_per_asset_ratio(&self) -> Decimal;
   }
 }
 
 blueprint! {
 
     struct FlashLoanService {
+        // A FlashLoanPool, or a WeightedPool holding several assets when weighted is set
         flashloanpool: ComponentAddress,
         flashloanpool_admin_badge: Vault,
         poolmanager:ComponentAddress,
         poolmanager_admin_badge: Vault,
+        weighted: bool,
+        loan_stats: HashMap<ResourceAddress, LoanStats>,
+        // Pool amount per LP token, as it was at the end of every epoch in which it changed
+        lp_value_history: BTreeMap<u64, Decimal>,
     }
 
     impl FlashLoanService {
@@ -54,12 +97,38 @@ blueprint! {
                 poolmanager,
                 flashloanpool_admin_badge: Vault::with_bucket(flashloanpool_admin_badge),
                 poolmanager_admin_badge: Vault::with_bucket(poolmanager_admin_badge),
+                weighted: false,
+                loan_stats: HashMap::new(),
+                lp_value_history: BTreeMap::from([(Runtime::current_epoch(), Decimal::one())]),
 
             }.instantiate().globalize()
 
         }
 
-        pub fn add_liquidity(&self,tokens:Bucket) -> Bucket{
+        /// Creates a service over a weighted pool of 2 to 8 assets, see WeightedPool::instantiate_weighted.
+        /// LP tokens are minted by the PoolManager for the value each deposit adds to the pool.
+        pub fn new_weighted(weightedpool_package_address:PackageAddress,poolmanager_package_address:PackageAddress,resources: Vec<ResourceAddress>,weights: Vec<Decimal>,swap_fee: Decimal,lp_symbol: String, lp_name: String) -> ComponentAddress{
+
+            let (flashloanpool,flashloanpool_admin_badge) =   WeightedPoolTarget::at(weightedpool_package_address,"WeightedPool").instantiate_weighted(resources,weights,swap_fee,dec!("0.01"));
+
+            let (poolmanager,poolmanager_admin_badge) =   PoolManagerTarget::at(poolmanager_package_address,"PoolManager").new(lp_symbol,lp_name);
+
+            Self {
+                flashloanpool,
+                poolmanager,
+                flashloanpool_admin_badge: Vault::with_bucket(flashloanpool_admin_badge),
+                poolmanager_admin_badge: Vault::with_bucket(poolmanager_admin_badge),
+                weighted: true,
+                loan_stats: HashMap::new(),
+                lp_value_history: BTreeMap::from([(Runtime::current_epoch(), Decimal::one())]),
+
+            }.instantiate().globalize()
+
+        }
+
+        pub fn add_liquidity(&mut self,tokens:Bucket) -> Bucket{
+            assert!(!self.weighted, "Use add_weighted_liquidity on a weighted pool");
+
             ComponentAuthZone::push(self.poolmanager_admin_badge.create_proof());
             ComponentAuthZone::push(self.flashloanpool_admin_badge.create_proof());
 
@@ -74,10 +143,14 @@ blueprint! {
             ComponentAuthZone::pop().drop();
             ComponentAuthZone::pop().drop();
 
+            self.record_lp_value();
+
             lp_tokens
         }
 
-        pub fn remove_liquidity(&self,lp_tokens:Bucket) -> Bucket{
+        pub fn remove_liquidity(&mut self,lp_tokens:Bucket) -> Bucket{
+            assert!(!self.weighted, "Use remove_weighted_liquidity on a weighted pool");
+
             ComponentAuthZone::push(self.poolmanager_admin_badge.create_proof());
             ComponentAuthZone::push(self.flashloanpool_admin_badge.create_proof());
 
@@ -91,19 +164,171 @@ blueprint! {
             ComponentAuthZone::pop().drop();
             ComponentAuthZone::pop().drop();
 
+            self.record_lp_value();
+
             tokens
         }
 
-        pub fn simulate_fee(&self,tokens:Bucket) {
+        /// Adds liquidity to a weighted pool. The first deposit must hold every asset,
+        /// afterwards any of them can be deposited single-sided.
+        pub fn add_weighted_liquidity(&mut self,tokens:Vec<Bucket>) -> Bucket{
+            assert!(self.weighted, "Not a weighted pool");
 
+            ComponentAuthZone::push(self.poolmanager_admin_badge.create_proof());
             ComponentAuthZone::push(self.flashloanpool_admin_badge.create_proof());
 
-            FlashLoanPoolComponentTarget::at(self.flashloanpool).add_liquidity(tokens);
+            // The value of the pool plays the part of the pool amount
+            let pool_value =  WeightedPoolComponentTarget::at(self.flashloanpool).invariant();
+
+            //
+            let value_added = WeightedPoolComponentTarget::at(self.flashloanpool).add_liquidity(tokens);
+            let lp_tokens =  PoolManagerComponentTarget::at(self.poolmanager).add_liquidity(value_added,pool_value);
 
             ComponentAuthZone::pop().drop();
+            ComponentAuthZone::pop().drop();
+
+            self.record_lp_value();
+
+            lp_tokens
         }
 
+        /// Removes liquidity from a weighted pool, as a proportional share of every asset
+        pub fn remove_weighted_liquidity(&mut self,lp_tokens:Bucket) -> Vec<Bucket>{
+            assert!(self.weighted, "Not a weighted pool");
+
+            ComponentAuthZone::push(self.poolmanager_admin_badge.create_proof());
+            ComponentAuthZone::push(self.flashloanpool_admin_badge.create_proof());
+
+            //
+            let pool_value =  WeightedPoolComponentTarget::at(self.flashloanpool).invariant();
+
+            //
+            let value_removed =  PoolManagerComponentTarget::at(self.poolmanager).remove_liquidity(lp_tokens,pool_value);
+            let tokens = WeightedPoolComponentTarget::at(self.flashloanpool).remove_liquidity(value_removed);
+
+            ComponentAuthZone::pop().drop();
+            ComponentAuthZone::pop().drop();
+
+            self.record_lp_value();
 
+            tokens
+        }
+
+        /// Removes liquidity from a weighted pool as a single asset
+        pub fn remove_weighted_liquidity_single(&mut self,lp_tokens:Bucket,resource_address:ResourceAddress) -> Bucket{
+            assert!(self.weighted, "Not a weighted pool");
+
+            ComponentAuthZone::push(self.poolmanager_admin_badge.create_proof());
+            ComponentAuthZone::push(self.flashloanpool_admin_badge.create_proof());
+
+            //
+            let pool_value =  WeightedPoolComponentTarget::at(self.flashloanpool).invariant();
+
+            //
+            let value_removed =  PoolManagerComponentTarget::at(self.poolmanager).remove_liquidity(lp_tokens,pool_value);
+            let tokens = WeightedPoolComponentTarget::at(self.flashloanpool).remove_liquidity_single(value_removed,resource_address);
+
+            ComponentAuthZone::pop().drop();
+            ComponentAuthZone::pop().drop();
+
+            self.record_lp_value();
+
+            tokens
+        }
+
+        /// Takes a flash loan of any asset of the pool. The returned loan terms can't be deposited,
+        /// they must be passed back to repay_loan in the same transaction.
+        pub fn take_loan(&self,resource_address:ResourceAddress,loan_amount:Decimal) -> (Bucket, Bucket){
+            ComponentAuthZone::push(self.flashloanpool_admin_badge.create_proof());
+
+            let loan = if self.weighted {
+                WeightedPoolComponentTarget::at(self.flashloanpool).take_loan(resource_address,loan_amount)
+            } else {
+                assert!(
+                    resource_address == FlashLoanPoolComponentTarget::at(self.flashloanpool).resource_address(),
+                    "Resource is not in the pool"
+                );
+                FlashLoanPoolComponentTarget::at(self.flashloanpool).take_loan(loan_amount)
+            };
+
+            ComponentAuthZone::pop().drop();
+
+            loan
+        }
+
+        /// Repays a flash loan. Its fee stays in the pool, raising the amount every LP token is worth
+        pub fn repay_loan(&mut self,loan_repayment:Bucket,loan_terms:Bucket) -> Bucket{
+            ComponentAuthZone::push(self.flashloanpool_admin_badge.create_proof());
+
+            // Read the terms before they are burned by the pool
+            let (resource_address, loan_amount, fees, change) = if self.weighted {
+                let terms: WeightedLoanDue = loan_terms.non_fungible().data();
+                let change = WeightedPoolComponentTarget::at(self.flashloanpool).repay_loan(loan_repayment,loan_terms);
+                (terms.resource_address, terms.loan_amount, terms.fees, change)
+            } else {
+                let terms: LoanDue = loan_terms.non_fungible().data();
+                let change = FlashLoanPoolComponentTarget::at(self.flashloanpool).repay_loan(loan_repayment,loan_terms);
+                (change.resource_address(), terms.loan_amount, terms.fees, change)
+            };
+
+            ComponentAuthZone::pop().drop();
+
+            let stats = self.loan_stats.entry(resource_address).or_insert(LoanStats {
+                loan_count: 0,
+                volume: Decimal::zero(),
+                fees_earned: Decimal::zero(),
+            });
+            stats.loan_count += 1;
+            stats.volume = stats.volume + loan_amount;
+            stats.fees_earned = stats.fees_earned + fees;
+
+            self.record_lp_value();
+
+            change
+        }
+
+        pub fn loan_stats(&self) -> HashMap<ResourceAddress, LoanStats> {
+            self.loan_stats.clone()
+        }
+
+        /// Pool amount a LP token was worth at the end of an epoch
+        pub fn lp_value(&self, epoch: u64) -> Decimal {
+            assert!(epoch <= Runtime::current_epoch(), "The epoch is in the future");
+            *self.lp_value_history
+                .range(..=epoch)
+                .next_back()
+                .expect("The pool didn't exist at this epoch")
+                .1
+        }
+
+        /// Yearly return realised by LP tokens held between two epochs, not compounded
+        pub fn realised_apr(&self, from_epoch: u64, to_epoch: u64) -> Decimal {
+            assert!(from_epoch < to_epoch, "The epoch window must end after it starts");
+            let from_value = self.lp_value(from_epoch);
+            let to_value = self.lp_value(to_epoch);
+            (to_value - from_value) / from_value * Decimal::from(EPOCHS_PER_YEAR) / Decimal::from(to_epoch - from_epoch)
+        }
+
+        fn pool_amount(&self) -> Decimal {
+            if self.weighted {
+                WeightedPoolComponentTarget::at(self.flashloanpool).invariant()
+            } else {
+                FlashLoanPoolComponentTarget::at(self.flashloanpool).available_liquidity()
+            }
+        }
+
+        // Adjust the LP per asset ratio to the pool amount and keep the resulting LP value for this epoch
+        fn record_lp_value(&mut self) {
+            ComponentAuthZone::push(self.poolmanager_admin_badge.create_proof());
+
+            let pool_amount = self.pool_amount();
+            PoolManagerComponentTarget::at(self.poolmanager).ajust_lp_per_asset_ratio(pool_amount);
+            let lp_per_asset_ratio = PoolManagerComponentTarget::at(self.poolmanager).lp_per_asset_ratio();
+
+            ComponentAuthZone::pop().drop();
+
+            self.lp_value_history.insert(Runtime::current_epoch(), Decimal::one() / lp_per_asset_ratio);
+        }
     }
 
 }
@@ -116,6 +116,10 @@ blueprint! {
             withdrawn
         }
 
+        pub fn lp_per_asset_ratio(&self) -> Decimal {
+            self.lp_per_asset_ratio
+        }
+
         // Collect fee for liquidity provider. will be added to the pool and LP per asset ratio will be ajusted to reflectthe new pool size (Liquidity + Fee)
        pub fn ajust_lp_per_asset_ratio(&mut self,
             pool_amount: Decimal
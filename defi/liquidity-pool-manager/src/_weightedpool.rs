@@ -0,0 +1,542 @@
+use scrypto::prelude::*;
+
+// A weighted pool holds 2 to 8 assets, each with a fixed weight, and keeps the weighted product
+// of its balances, B1^w1 * B2^w2 * ..., constant on swaps (Balancer style).
+// The weighted product is also the value of the pool: LP tokens are minted by the PoolManager per unit of it.
+pub const MIN_ASSETS: usize = 2;
+pub const MAX_ASSETS: usize = 8;
+
+// Single-sided deposits and swaps can bring in at most half of the balance of an asset,
+// single-sided withdrawals and swaps can take out at most a third of it.
+fn max_in_ratio() -> Decimal {
+    dec!("0.5")
+}
+
+fn max_out_ratio() -> Decimal {
+    dec!("0.333333333333333333")
+}
+
+// The series in pow are only exact to about 1e-16 relative, results which depend on it
+// are moved by this much in favour of the pool so the rounding never pays out
+fn pow_tolerance() -> Decimal {
+    dec!("0.000000000001")
+}
+
+fn ln2() -> Decimal {
+    dec!("0.693147180559945309")
+}
+
+/// Natural logarithm of a positive number
+pub fn ln(x: Decimal) -> Decimal {
+    assert!(x > Decimal::zero(), "Logarithm of a non-positive number");
+    let two = dec!("2");
+
+    // Scale x into [1, 2), counting the powers of two taken out
+    let mut m = x;
+    let mut k: i32 = 0;
+    while m >= two {
+        m = m / two;
+        k += 1;
+    }
+    while m < Decimal::one() {
+        m = m * two;
+        k -= 1;
+    }
+
+    // ln(m) = 2 * atanh(z) with z = (m - 1) / (m + 1), which is at most 1/3
+    let z = (m - Decimal::one()) / (m + Decimal::one());
+    let z2 = z * z;
+    let mut term = z;
+    let mut sum = Decimal::zero();
+    let mut n: i32 = 1;
+    while !term.is_zero() {
+        sum = sum + term / Decimal::from(n);
+        term = term * z2;
+        n += 2;
+    }
+
+    Decimal::from(k) * ln2() + sum * two
+}
+
+/// Natural exponential of a number
+pub fn exp(y: Decimal) -> Decimal {
+    let two = dec!("2");
+    let half_ln2 = ln2() / two;
+
+    // e^y = 2^k * e^r with r within half of ln(2) of zero
+    let mut r = y;
+    let mut k: i32 = 0;
+    while r > half_ln2 {
+        r = r - ln2();
+        k += 1;
+    }
+    while r < -half_ln2 {
+        r = r + ln2();
+        k -= 1;
+    }
+
+    let mut term = Decimal::one();
+    let mut sum = Decimal::one();
+    let mut n: i32 = 1;
+    while !term.is_zero() {
+        term = term * r / Decimal::from(n);
+        sum = sum + term;
+        n += 1;
+    }
+
+    while k > 0 {
+        sum = sum * two;
+        k -= 1;
+    }
+    while k < 0 {
+        sum = sum / two;
+        k += 1;
+    }
+    sum
+}
+
+/// A non-negative number to the power of any exponent
+pub fn pow(base: Decimal, exponent: Decimal) -> Decimal {
+    if base.is_zero() {
+        return Decimal::zero();
+    }
+    exp(exponent * ln(base))
+}
+
+/// Weighted product of the balances, zero if any of them is empty.
+/// Takes (balance, weight) pairs, with weights summing to one.
+pub fn invariant(balances: &[(Decimal, Decimal)]) -> Decimal {
+    if balances.iter().any(|(balance, _)| balance.is_zero()) {
+        return Decimal::zero();
+    }
+    exp(balances.iter().fold(Decimal::zero(), |sum, (balance, weight)| sum + *weight * ln(*balance)))
+}
+
+/// Output of a swap keeping the weighted product constant, the fee stays in the pool
+pub fn swap_output(
+    input_balance: Decimal,
+    input_weight: Decimal,
+    output_balance: Decimal,
+    output_weight: Decimal,
+    input_amount: Decimal,
+    fee: Decimal,
+) -> Decimal {
+    let input_after_fee = input_amount * (Decimal::one() - fee);
+    let ratio = input_balance / (input_balance + input_after_fee);
+    let remaining = output_balance * pow(ratio, input_weight / output_weight) * (Decimal::one() + pow_tolerance());
+    if remaining >= output_balance {
+        return Decimal::zero();
+    }
+    output_balance - remaining
+}
+
+/// Value added to the pool by depositing a single asset.
+/// Only the part of the deposit which would have to be swapped to the other assets pays the fee.
+pub fn single_deposit_value(
+    value: Decimal,
+    balance: Decimal,
+    weight: Decimal,
+    amount: Decimal,
+    fee: Decimal,
+) -> Decimal {
+    let amount_after_fee = amount * (Decimal::one() - fee * (Decimal::one() - weight));
+    let new_value = value * pow(Decimal::one() + amount_after_fee / balance, weight) * (Decimal::one() - pow_tolerance());
+    if new_value <= value {
+        return Decimal::zero();
+    }
+    new_value - value
+}
+
+/// Amount of a single asset returned for taking some value out of the pool.
+/// Only the part of the withdrawal which would have to be swapped from the other assets pays the fee.
+pub fn single_withdrawal_output(
+    value: Decimal,
+    balance: Decimal,
+    weight: Decimal,
+    value_removed: Decimal,
+    fee: Decimal,
+) -> Decimal {
+    let remaining = pow((value - value_removed) / value, Decimal::one() / weight) * (Decimal::one() + pow_tolerance());
+    if remaining >= Decimal::one() {
+        return Decimal::zero();
+    }
+    (balance - balance * remaining) * (Decimal::one() - fee * (Decimal::one() - weight))
+}
+
+#[derive(NonFungibleData)]
+pub struct WeightedLoanDue {
+    pub resource_address: ResourceAddress,
+    pub amount_due: Decimal,
+    pub loan_amount: Decimal,
+    pub fees: Decimal,
+}
+
+blueprint! {
+    struct WeightedPool {
+        vaults: HashMap<ResourceAddress, Vault>,
+        weights: HashMap<ResourceAddress, Decimal>,
+        swap_fee: Decimal,
+        loan_fee_rate: Decimal,
+        auth_vault: Vault,
+        transient_resource_address: ResourceAddress,
+    }
+
+    impl WeightedPool {
+        /// Creates a pool of 2 to 8 assets with fixed weights, which are scaled to sum to one.
+        /// Swaps pay the swap fee and flash loans of any asset pay the loan fee rate, both stay in the pool.
+        ///
+        /// Liquidity is added and removed, and flash loans are taken, through the admin, which is expected
+        /// to mint and burn LP tokens for the value returned, see FlashLoanService::new_weighted.
+        pub fn instantiate_weighted(
+            resources: Vec<ResourceAddress>,
+            weights: Vec<Decimal>,
+            swap_fee: Decimal,
+            loan_fee_rate: Decimal,
+        ) -> (ComponentAddress,Bucket) {
+
+            assert!(
+                resources.len() >= MIN_ASSETS && resources.len() <= MAX_ASSETS,
+                "A weighted pool holds between {} and {} assets", MIN_ASSETS, MAX_ASSETS
+            );
+            assert!(resources.len() == weights.len(), "Every asset needs a weight");
+            assert!(
+                weights.iter().all(|weight| *weight > Decimal::zero()),
+                "Weights must be positive"
+            );
+            assert!(
+                swap_fee >= Decimal::zero() && swap_fee < Decimal::one(),
+                "Swap fee must be between 0 and 1"
+            );
+            assert!(
+                loan_fee_rate > dec!(0),
+                "Loan interest can't be negative"
+            );
+
+            let total_weight = weights.iter().fold(Decimal::zero(), |sum, weight| sum + *weight);
+            let mut vaults = HashMap::new();
+            let mut normalized_weights = HashMap::new();
+            for (resource, weight) in resources.into_iter().zip(weights) {
+                assert!(!vaults.contains_key(&resource), "An asset can only be in the pool once");
+                vaults.insert(resource, Vault::new(resource));
+                normalized_weights.insert(resource, weight / total_weight);
+            }
+
+            let auth_token = ResourceBuilder::new_fungible()
+                .divisibility(DIVISIBILITY_NONE)
+                .metadata("name", "Admin authority for WeightedPool")
+                .initial_supply(1);
+
+            // Define a "transient" resource which can never be deposited once created, only burned
+            let address = ResourceBuilder::new_non_fungible(NonFungibleIdType::UUID)
+                .metadata(
+                    "name",
+                    "Promise token for WeightedPool - must be returned to be burned!",
+                )
+                .mintable(rule!(require(auth_token.resource_address())), AccessRule::DenyAll)
+                .burnable(rule!(require(auth_token.resource_address())), AccessRule::DenyAll)
+                .restrict_deposit(AccessRule::DenyAll, AccessRule::DenyAll)
+                .no_initial_supply();
+
+            //  Lock liquidity and loan methods to admin only
+
+            let admin_badge = ResourceBuilder::new_fungible()
+            .divisibility(DIVISIBILITY_NONE)
+            .metadata("name", "WeightedPool admin bage")
+            .initial_supply(1);
+
+            let mut weighted_pool_component = Self {
+                vaults,
+                weights: normalized_weights,
+                swap_fee,
+                loan_fee_rate,
+                auth_vault: Vault::with_bucket(auth_token),
+                transient_resource_address: address,
+            }
+            .instantiate();
+
+            let access_rules = AccessRules::new()
+                .method("add_liquidity", rule!(require(admin_badge.resource_address())), AccessRule::DenyAll)
+                .method("remove_liquidity", rule!(require(admin_badge.resource_address())), AccessRule::DenyAll)
+                .method("take_loan", rule!(require(admin_badge.resource_address())), AccessRule::DenyAll)
+                .method("repay_loan", rule!(require(admin_badge.resource_address())), AccessRule::DenyAll)
+                .method("remove_liquidity_single", rule!(require(admin_badge.resource_address())), AccessRule::DenyAll)
+                .default(AccessRule::AllowAll, AccessRule::DenyAll);
+
+            weighted_pool_component.add_access_check(access_rules);
+
+            (weighted_pool_component.globalize(),admin_badge)
+        }
+
+        pub fn weights(&self) -> HashMap<ResourceAddress, Decimal> {
+            self.weights.clone()
+        }
+
+        pub fn balance(&self, resource_address: ResourceAddress) -> Decimal {
+            self.vault(resource_address).amount()
+        }
+
+        /// Value of the pool, the weighted product of the balances
+        pub fn invariant(&self) -> Decimal {
+            let balances: Vec<(Decimal, Decimal)> = self.weights
+                .iter()
+                .map(|(resource, weight)| (self.vaults[resource].amount(), *weight))
+                .collect();
+            invariant(&balances)
+        }
+
+        /// Amount of the input asset paid for one unit of the output asset, fee excluded
+        pub fn spot_price(&self, input_resource: ResourceAddress, output_resource: ResourceAddress) -> Decimal {
+            let input = self.balance(input_resource) / self.weights[&input_resource];
+            let output = self.balance(output_resource) / self.weights[&output_resource];
+            assert!(!output.is_zero(), "The pool is empty");
+            input / output
+        }
+
+        /// Adds liquidity and returns the value it added to the pool.
+        /// The first deposit must hold every asset, and sets their prices. Afterwards assets are deposited
+        /// one at a time, single-sided, paying the swap fee on the part which isn't proportional to the pool.
+        pub fn add_liquidity(&mut self, tokens: Vec<Bucket>) -> Decimal {
+            let value = self.invariant();
+
+            if value.is_zero() {
+                assert!(
+                    tokens.len() == self.vaults.len()
+                        && tokens.iter().all(|bucket| !bucket.is_empty()),
+                    "The first deposit must hold every asset of the pool"
+                );
+                for bucket in tokens {
+                    let resource_address = bucket.resource_address();
+                    assert!(self.vault(resource_address).is_empty(), "An asset can only be deposited once");
+                    self.vault_mut(resource_address).put(bucket);
+                }
+                return self.invariant();
+            }
+
+            let mut added = Decimal::zero();
+            for bucket in tokens {
+                let resource_address = bucket.resource_address();
+                let balance = self.balance(resource_address);
+                assert!(
+                    bucket.amount() <= balance * max_in_ratio(),
+                    "A single-sided deposit can be at most half of the balance of the asset"
+                );
+                added = added + single_deposit_value(
+                    value + added,
+                    balance,
+                    self.weights[&resource_address],
+                    bucket.amount(),
+                    self.swap_fee,
+                );
+                self.vault_mut(resource_address).put(bucket);
+            }
+            added
+        }
+
+        /// Removes a share of every asset, proportional to the value taken out of the pool
+        pub fn remove_liquidity(&mut self, value_removed: Decimal) -> Vec<Bucket> {
+            let value = self.invariant();
+            assert!(value_removed <= value, "Not enough liquidity in the pool");
+
+            self.vaults
+                .values_mut()
+                .map(|vault| {
+                    // Take the whole balance on the last withdrawal to deal with rounding issue
+                    if value_removed == value {
+                        vault.take_all()
+                    } else {
+                        let amount = vault.amount() * value_removed / value;
+                        vault.take(amount)
+                    }
+                })
+                .collect()
+        }
+
+        /// Removes a single asset for the value taken out of the pool
+        pub fn remove_liquidity_single(&mut self, value_removed: Decimal, resource_address: ResourceAddress) -> Bucket {
+            let value = self.invariant();
+            assert!(value_removed <= value, "Not enough liquidity in the pool");
+
+            let balance = self.balance(resource_address);
+            let amount = single_withdrawal_output(
+                value,
+                balance,
+                self.weights[&resource_address],
+                value_removed,
+                self.swap_fee,
+            );
+            assert!(
+                amount <= balance * max_out_ratio(),
+                "A single-sided withdrawal can be at most a third of the balance of the asset"
+            );
+            self.vault_mut(resource_address).take(amount)
+        }
+
+        /// Swaps the input for another asset of the pool, keeping the weighted product constant
+        pub fn swap(&mut self, input: Bucket, output_resource: ResourceAddress, min_output: Decimal) -> Bucket {
+            let input_resource = input.resource_address();
+            assert!(input_resource != output_resource, "Can't swap an asset for itself");
+
+            let input_balance = self.balance(input_resource);
+            let output_balance = self.balance(output_resource);
+            assert!(
+                !input_balance.is_zero() && !output_balance.is_zero(),
+                "The pool is empty"
+            );
+            assert!(
+                input.amount() <= input_balance * max_in_ratio(),
+                "A swap can bring in at most half of the balance of the input asset"
+            );
+
+            let output = swap_output(
+                input_balance,
+                self.weights[&input_resource],
+                output_balance,
+                self.weights[&output_resource],
+                input.amount(),
+                self.swap_fee,
+            );
+            assert!(
+                output <= output_balance * max_out_ratio(),
+                "A swap can take out at most a third of the balance of the output asset"
+            );
+            assert!(
+                output >= min_output,
+                "Slippage exceeded: would receive {} but the minimum is {}", output, min_output
+            );
+
+            self.vault_mut(input_resource).put(input);
+            self.vault_mut(output_resource).take(output)
+        }
+
+        pub fn take_loan(&mut self, resource_address: ResourceAddress, loan_amount: Decimal) -> (Bucket, Bucket) {
+            assert!(loan_amount > Decimal::zero(), "The loan amount must be positive");
+            assert!(
+                loan_amount <= self.balance(resource_address),
+                "Not enough liquidity to supply this loan!"
+            );
+
+            // Calculate how much we must be repaid
+
+            let fees = loan_amount * (self.loan_fee_rate);
+
+            // Mint an NFT with the loan terms, which can only be burned by repay_loan(). See FlashLoanPool::take_loan
+            let loan_terms = self.auth_vault.authorize(|| {
+                borrow_resource_manager!(self.transient_resource_address).mint_non_fungible(
+                    &NonFungibleId::random(),
+                    WeightedLoanDue {
+                        resource_address,
+                        amount_due: fees + loan_amount,
+                        fees,
+                        loan_amount,
+                    },
+                )
+            });
+            (self.vault_mut(resource_address).take(loan_amount), loan_terms)
+        }
+
+        pub fn repay_loan(&mut self, mut loan_repayment: Bucket, loan_terms: Bucket) -> Bucket{
+            assert!(
+                loan_terms.resource_address() == self.transient_resource_address,
+                "Incorrect resource passed in for loan terms"
+            );
+
+            let terms: WeightedLoanDue = loan_terms.non_fungible().data();
+            assert!(
+                loan_repayment.resource_address() == terms.resource_address,
+                "The loan must be repaid with the asset that was lent"
+            );
+            assert!(
+                loan_repayment.amount() >= terms.amount_due,
+                "Insufficient repayment given for your loan!"
+            );
+
+            // The fees stay in the pool, raising its value for the liquidity providers
+            self.vault_mut(terms.resource_address).put(loan_repayment.take(terms.amount_due));
+
+            self.auth_vault.authorize(|| loan_terms.burn());
+
+            // Return the change to the work top
+            loan_repayment
+        }
+
+        fn vault(&self, resource_address: ResourceAddress) -> &Vault {
+            self.vaults.get(&resource_address).expect("Resource is not in the pool")
+        }
+
+        fn vault_mut(&mut self, resource_address: ResourceAddress) -> &mut Vault {
+            self.vaults.get_mut(&resource_address).expect("Resource is not in the pool")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: Decimal, expected: Decimal, tolerance: Decimal) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{} is not within {} of {}", actual, tolerance, expected
+        );
+    }
+
+    fn weighted(balances: &[Decimal; 3]) -> Vec<(Decimal, Decimal)> {
+        vec![
+            (balances[0], dec!("0.5")),
+            (balances[1], dec!("0.3")),
+            (balances[2], dec!("0.2")),
+        ]
+    }
+
+    #[test]
+    fn test_ln_exp() {
+        let tolerance = dec!("0.000000000001");
+        let e = dec!("2.718281828459045235");
+
+        assert_eq!(ln(Decimal::one()), Decimal::zero());
+        assert_close(ln(dec!("2")), ln2(), tolerance);
+        assert_close(ln(dec!("0.5")), -ln2(), tolerance);
+        assert_close(ln(e), Decimal::one(), tolerance);
+        assert_close(ln(dec!("1000")), dec!("6.907755278982137052"), tolerance);
+
+        assert_eq!(exp(Decimal::zero()), Decimal::one());
+        assert_close(exp(Decimal::one()), e, tolerance);
+        assert_close(exp(dec!("-1")), dec!("0.367879441171442321"), tolerance);
+        assert_close(exp(ln(dec!("12345.678"))), dec!("12345.678"), dec!("0.0000001"));
+
+        assert_close(pow(dec!("16"), dec!("0.25")), dec!("2"), tolerance);
+        assert_close(pow(dec!("2"), dec!("10")), dec!("1024"), dec!("0.000000001"));
+    }
+
+    #[test]
+    fn test_swap_keeps_invariant() {
+        let mut balances = [dec!("1000"), dec!("2000"), dec!("500")];
+        let before = invariant(&weighted(&balances));
+
+        let input = dec!("100");
+        let output = swap_output(balances[0], dec!("0.5"), balances[1], dec!("0.3"), input, Decimal::zero());
+        assert!(output > Decimal::zero());
+        balances[0] = balances[0] + input;
+        balances[1] = balances[1] - output;
+
+        let after = invariant(&weighted(&balances));
+        assert_close(after, before, dec!("0.000000001"));
+        assert!(after >= before, "The invariant went down from {} to {}", before, after);
+    }
+
+    #[test]
+    fn test_single_deposit_and_withdrawal_round_trip() {
+        for fee in [Decimal::zero(), dec!("0.003")] {
+            let mut balances = [dec!("1000"), dec!("2000"), dec!("500")];
+            let value = invariant(&weighted(&balances));
+
+            let deposit = dec!("200");
+            let added = single_deposit_value(value, balances[0], dec!("0.5"), deposit, fee);
+            balances[0] = balances[0] + deposit;
+            let value = value + added;
+
+            let output = single_withdrawal_output(value, balances[0], dec!("0.5"), added, fee);
+            assert!(output <= deposit, "{} out for {} in with a fee of {}", output, deposit, fee);
+        }
+    }
+}
@@ -3,3 +3,4 @@ use scrypto::prelude::*;
 mod _flashloanpool;
 mod _flashloanservice;
 mod _poolmanager;
+mod _weightedpool;
@@ -72,16 +72,22 @@ setEnvValue('transient_token', transient_token);
 await e($`resim set-default-account ${account1} ${privkey1}`)
 await e($`resim call-method ${service_component} add_liquidity 900,${tokenXRD}`)
 
-// simulate pool first fee collection
+// simulate pool first fee collection, one epoch later
+await e($`resim set-current-epoch 1`)
 await take_flash_loan(e, 2);
 
 // add same amount fo liquidity from account2
 await e($`resim set-default-account ${account2} ${privkey2}`)
 await e($`resim call-method ${service_component} add_liquidity 900,${tokenXRD}`)
 
-// simulate pool first fee collection
+// simulate pool second fee collection, one epoch later
+await e($`resim set-current-epoch 2`)
 await take_flash_loan(e, 2);
 
+// Loan count, volume and fees earned, and the APR realised by LPs over both epochs
+await e($`resim call-method ${service_component} loan_stats`, false)
+await e($`resim call-method ${service_component} realised_apr 0 2`, false)
+
 // Remove all liquidity in the pool
 await e($`resim set-default-account ${account1} ${privkey1}`)
 await e($`resim call-method ${service_component} remove_liquidity 900,${lp_token}`)
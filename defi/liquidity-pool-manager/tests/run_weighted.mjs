@@ -0,0 +1,86 @@
+#!/usr/bin/env zx
+
+/**
+ * run following commands for test
+ *      npx zx ./run_weighted.mjs && source .env      
+ */
+
+const { setEnvValue, resetEnvs, e } = require('./utils')
+
+// resetEnvs()
+
+await $`resim reset`
+
+const tokenXRD = "resource_sim1qzkcyv5dwq3r6kawy6pxpvcythx8rh8ntum6ws62p95sqjjpwr"
+setEnvValue('tokenXRD', tokenXRD);
+
+//Setting up 2 account, 1 account for swaps and flash loans, 1 account for liquidity providing
+
+let output = await e($`resim new-account`)
+const account = output[0]
+const pubkey = output[1]
+const privkey = output[2]
+setEnvValue('account', account);
+setEnvValue('privkey', privkey);
+
+output = await e($`resim new-account`)
+const account1 = output[0]
+const pubkey1 = output[1]
+const privkey1 = output[2]
+setEnvValue('account1', account1);
+setEnvValue('privkey1', privkey1);
+
+// Two more assets for the pool, held by account1 which transfers some to account
+await e($`resim set-default-account ${account1} ${privkey1}`)
+const tokenA = (await e($`resim new-token-fixed --symbol A 100000`))[0]
+const tokenB = (await e($`resim new-token-fixed --symbol B 100000`))[0]
+setEnvValue('tokenA', tokenA);
+setEnvValue('tokenB', tokenB);
+await e($`resim transfer 1000 ${tokenA} ${account}`)
+
+output = await e($`resim new-simple-badge`)
+const deploy_badge = output[0]
+
+const pub_package = (await e($`resim publish .. --owner-badge ${deploy_badge}`))[0]
+setEnvValue('package', pub_package);
+
+// Create an empty weighted pool of XRD, A and B, weighted 50/30/20
+setEnvValue('account', account1);
+output = await e($`resim run ./rtm/weighted_new.rtm`, false)
+
+const weightedpool_component = output[0]
+const poolmanager_component = output[1]
+const service_component = output[2]
+
+//
+const transient_mint_badge = output[3]
+const transient_token = output[4]
+const weightedpool_admin_badge = output[5]
+const lp_mint_badge = output[6]
+const poolmanager_admin_badge = output[7]
+const lp_token = output[8]
+
+setEnvValue('weightedpool_component', weightedpool_component);
+setEnvValue('poolmanager_component', poolmanager_component);
+setEnvValue('service_component', service_component);
+setEnvValue('lp_token', lp_token);
+setEnvValue('transient_token', transient_token);
+
+// Seed the pool with every asset from account1, then add A single-sided
+await e($`resim run ./rtm/weighted_add_liquidity.rtm`)
+await e($`resim run ./rtm/weighted_add_single.rtm`)
+await e($`resim show ${account1}`, false)
+
+// Swap and take a flash loan of A from account
+await e($`resim set-default-account ${account} ${privkey}`)
+setEnvValue('account', account);
+await e($`resim call-method ${weightedpool_component} swap 100,${tokenA} ${tokenB} 0`)
+await e($`resim run ./rtm/weighted_flash_loan.rtm`)
+await e($`resim show ${account}`, false)
+
+// Remove part of the liquidity as B only, then the rest proportionally
+await e($`resim set-default-account ${account1} ${privkey1}`)
+await e($`resim call-method ${service_component} remove_weighted_liquidity_single 50,${lp_token} ${tokenB}`)
+output = await e($`resim show ${account1}`, false)
+await e($`resim call-method ${service_component} remove_weighted_liquidity 300,${lp_token}`)
+await e($`resim show ${weightedpool_component}`, false)
@@ -1,3 +1,6 @@
+use std::collections::VecDeque;
+
+use crate::model::FeeSchedule;
 use crate::trading_pair::*;
 use scrypto::prelude::*;
 
@@ -39,12 +42,18 @@ blueprint! {
         }
 
         /// Adds a trading pair for the given base_resource_address and quote_resource_address.
+        /// The trading pair charges the given maker_fee and taker_fee and pays makers the given maker_rebate.
+        /// All of them are fractions of the traded amount, e.g. 0.001 for 0.1%. The rebate is funded from the taker fee.
+        /// The collected fees can be withdrawn from the trading pair using the admin badge of this component.
         /// Checks that the same trading pair cannot be added twice.
         /// Does not check that the reverse of an existing trading pair is not added!
         pub fn add_trading_pair(
             &mut self,
             base_resource_address: ResourceAddress,
             quote_resource_address: ResourceAddress,
+            maker_fee: Decimal,
+            taker_fee: Decimal,
+            maker_rebate: Decimal,
         ) {
             // Prevent the same trading pair from being added twice
             assert!(
@@ -56,10 +65,15 @@ blueprint! {
                 quote_resource_address
             );
 
+            // Validate the fees. This panics if they are out of range.
+            let fee_schedule = FeeSchedule::new(maker_fee, taker_fee, maker_rebate);
+
             // Instantiate a new TradingPair component on the ledger
             let trainding_pair_component = TradingPair::instantiate(
                 base_resource_address,
                 quote_resource_address,
+                fee_schedule,
+                self.admin_badge,
             );
 
             // Save the info on the newly created trading pair in the trading_pairs HashMap
@@ -69,10 +83,49 @@ blueprint! {
                     base_resource_address,
                     quote_resource_address,
                     component_address: trainding_pair_component,
+                    fee_schedule,
                 },
             );
         }
 
+        /// Exchanges the given funds for the given target resource via one or more trading pairs.
+        /// The route with the fewest hops through the registered trading pairs is chosen, irrespective of whether a trading
+        /// pair is traversed from the base to the quote resource or the other way round. On every hop, a market order is
+        /// executed on the respective TradingPair component.
+        ///
+        /// Returns two values:
+        /// 1. The funds received in the target resource
+        /// 2. The dust of every hop, i.e. any funds that could not be exchanged on that hop
+        ///
+        /// Panics if no route exists, if any of the market orders fails or if less than min_output is received.
+        pub fn new_routed_market_order(
+            &mut self,
+            funds: Bucket,
+            target_resource_address: ResourceAddress,
+            min_output: Decimal,
+        ) -> (Bucket, Vec<Bucket>) {
+            let route = self.find_route(funds.resource_address(), target_resource_address);
+
+            // Execute a market order on every hop, feeding the traded funds of one hop into the next
+            let mut funds = funds;
+            let mut dust = Vec::new();
+            for component_address in route {
+                let (hop_dust, traded_funds) = borrow_component!(component_address)
+                    .call::<(Bucket, Option<Bucket>)>("new_market_order", args![funds]);
+                dust.push(hop_dust);
+                funds = traded_funds.expect("Insufficient funds: a hop of the route did not yield any funds");
+            }
+
+            assert!(
+                funds.amount() >= min_output,
+                "Insufficient output: received {} but expected at least {}",
+                funds.amount(),
+                min_output
+            );
+
+            (funds, dust)
+        }
+
         /// Returns a vector with all trading pairs that are managed by this component
         pub fn get_trading_pairs(&self) -> Vec<TradingPairInfo> {
             self.trading_pairs.values().cloned().collect()
@@ -89,6 +142,61 @@ blueprint! {
                 .get(&(base_resource_address, quote_resource_address))
                 .map(|trading_pair| trading_pair.component_address)
         }
+
+        /// Finds the route with the fewest hops from the given source resource to the given target resource.
+        /// Returns the addresses of the TradingPair components that make up the route, in the order they must be traversed.
+        /// Panics if no route exists.
+        fn find_route(
+            &self,
+            source_resource_address: ResourceAddress,
+            target_resource_address: ResourceAddress,
+        ) -> Vec<ComponentAddress> {
+            assert!(
+                source_resource_address != target_resource_address,
+                "Source and target resource must be different"
+            );
+
+            // Breadth first search over the resources, where every trading pair connects its base and quote resource.
+            // For every visited resource, remember the resource it has been reached from and via which trading pair.
+            let mut predecessors: HashMap<ResourceAddress, (ResourceAddress, ComponentAddress)> = HashMap::new();
+            let mut queue = VecDeque::from([source_resource_address]);
+            while let Some(resource_address) = queue.pop_front() {
+                if resource_address == target_resource_address {
+                    break;
+                }
+                for trading_pair in self.trading_pairs.values() {
+                    let next_resource_address = if trading_pair.base_resource_address == resource_address {
+                        trading_pair.quote_resource_address
+                    } else if trading_pair.quote_resource_address == resource_address {
+                        trading_pair.base_resource_address
+                    } else {
+                        continue;
+                    };
+                    if next_resource_address != source_resource_address
+                        && !predecessors.contains_key(&next_resource_address)
+                    {
+                        predecessors.insert(
+                            next_resource_address,
+                            (resource_address, trading_pair.component_address),
+                        );
+                        queue.push_back(next_resource_address);
+                    }
+                }
+            }
+
+            // Walk back from the target resource to the source resource
+            let mut route = Vec::new();
+            let mut resource_address = target_resource_address;
+            while resource_address != source_resource_address {
+                let (previous_resource_address, component_address) = *predecessors
+                    .get(&resource_address)
+                    .expect("No route found between the given resources");
+                route.push(component_address);
+                resource_address = previous_resource_address;
+            }
+            route.reverse();
+            route
+        }
     }
 }
 
@@ -103,4 +211,7 @@ pub struct TradingPairInfo {
 
     /// The address of TradingPair component that must be used to exchange resources
     pub component_address: ComponentAddress,
+
+    /// The fees charged by the trading pair
+    pub fee_schedule: FeeSchedule,
 }
@@ -19,8 +19,113 @@ impl Side {
     }
 }
 
+/// Represents the policy that determines whether a limit order is matched against the order book and for how long
+/// the part that cannot be matched is kept in the order book.
+#[derive(sbor::TypeId, sbor::Encode, sbor::Decode, sbor::Describe, Clone, Copy, Debug)]
+pub(crate) enum TimeInForce {
+    /// The order is never matched against the order book and is rejected if it would be. It rests in the order book
+    /// until it is closed.
+    PostOnly,
+    /// The order is matched against the order book as far as its price allows. The rest is refunded immediately.
+    ImmediateOrCancel,
+    /// The order must be matched against the order book completely, otherwise it is rejected.
+    FillOrKill,
+    /// The order is matched against the order book as far as its price allows. The rest rests in the order book
+    /// up to and including the given epoch. After that, it is no longer matched and can only be closed.
+    GoodTillEpoch(u64),
+}
+
+impl TimeInForce {
+    /// Returns true if an order with this time in force is no longer valid in the given epoch, else false
+    pub fn is_expired(&self, epoch: u64) -> bool {
+        match self {
+            Self::GoodTillEpoch(expiry_epoch) => epoch > *expiry_epoch,
+            _ => false,
+        }
+    }
+}
+
+/// Represents the fees charged by a trading pair. All fees are fractions of the traded amount, e.g. 0.001 for 0.1%.
+/// Takers pay their fee on top of the funds they spend, makers pay theirs out of the funds they receive.
+/// The maker rebate is paid to makers out of the taker fee.
+#[derive(sbor::TypeId, sbor::Encode, sbor::Decode, sbor::Describe, Clone, Copy, Debug)]
+pub struct FeeSchedule {
+    /// The fee charged to makers on the funds they receive when their limit order is filled
+    pub maker_fee: Decimal,
+
+    /// The fee charged to takers on the funds they spend when executing a market order
+    pub taker_fee: Decimal,
+
+    /// The rebate paid to makers on the funds they receive when their limit order is filled
+    pub maker_rebate: Decimal,
+}
+
+impl FeeSchedule {
+    /// Creates a new fee schedule
+    /// Panics if a fee or the rebate is not within [0, 1) or if the rebate is higher than the taker fee
+    pub fn new(maker_fee: Decimal, taker_fee: Decimal, maker_rebate: Decimal) -> Self {
+        for (name, fee) in [
+            ("maker_fee", maker_fee),
+            ("taker_fee", taker_fee),
+            ("maker_rebate", maker_rebate),
+        ] {
+            assert!(
+                !fee.is_negative() && fee < Decimal::one(),
+                "Parameter {} must be >= zero and < one",
+                name
+            );
+        }
+        assert!(
+            maker_rebate <= taker_fee,
+            "Parameter maker_rebate must not be higher than taker_fee"
+        );
+        Self {
+            maker_fee,
+            taker_fee,
+            maker_rebate,
+        }
+    }
+
+    /// Returns the fee effectively charged to makers, i.e. the maker fee minus the maker rebate.
+    /// This is negative if the rebate exceeds the maker fee.
+    pub fn net_maker_fee(&self) -> Decimal {
+        self.maker_fee - self.maker_rebate
+    }
+}
+
+/// Represents the direction in which the last traded price must cross a trigger price for a trigger order to fire.
+/// A stop-loss on an Ask order would e.g. use Below, while a take-profit on an Ask order would use Above.
+#[derive(sbor::TypeId, sbor::Encode, sbor::Decode, sbor::Describe, Clone, Copy, Debug)]
+pub(crate) enum TriggerDirection {
+    /// Fires once the last traded price is at or above the trigger price
+    Above,
+    /// Fires once the last traded price is at or below the trigger price
+    Below,
+}
+
+/// Represents the condition under which a trigger order is turned into a market order.
+#[derive(sbor::TypeId, sbor::Encode, sbor::Decode, sbor::Describe, Clone, Debug)]
+pub(crate) struct Trigger {
+    /// The price that the last traded price must cross for the trigger to fire.
+    /// Like the price of a limit order, this is the amount of the quote resource paid for one unit of the base resource.
+    pub price: Decimal,
+
+    /// The direction in which the last traded price must cross the trigger price
+    pub direction: TriggerDirection,
+}
+
+impl Trigger {
+    /// Returns true if the given last traded price satisfies this trigger, else false
+    pub fn is_met(&self, last_price: Decimal) -> bool {
+        match self.direction {
+            TriggerDirection::Above => last_price >= self.price,
+            TriggerDirection::Below => last_price <= self.price,
+        }
+    }
+}
+
 /// Represent a limit order in the order book.
-#[derive(sbor::TypeId, sbor::Encode, sbor::Decode, sbor::Describe, NonFungibleData)]
+#[derive(sbor::TypeId, sbor::Encode, sbor::Decode, sbor::Describe, NonFungibleData, Clone)]
 pub(crate) struct LimitOrder {
     /// A key that uniquely identifies the order
     pub order_key: NonFungibleId,
@@ -31,27 +136,58 @@ pub(crate) struct LimitOrder {
     /// The amount of the quote resource that must be paid to get one unit of the base resource.
     /// For a trading pair XRD/rUSD a price of 10 would mean that 10 rUSD would have to be paid in order to buy 1 XRD.
     /// The price is always defined as stated above, irrespective of the order side!
+    /// The price can be changed by amending the order.
+    #[scrypto(mutable)]
     pub price: Decimal,
 
     /// The amount of the resource that a user is providing to the DEX for their order.
     /// For a trading pair XRD/rUSD and an Ask order this would be the amount of XRD, as the user is asking rUSD for the XRD they are selling.
     /// For a Bid order this would be the amount of rUSD, as the user is bidding rUSD for the XRD they are wanting to buy.
     /// Note that this is asymmetric with respect to the price!
+    /// The quantity can be changed by amending the order.
+    #[scrypto(mutable)]
     pub quantity: Decimal,
 
     /// The amount of the order quantity that has already been filled.
     #[scrypto(mutable)]
     pub quantity_filled: Decimal,
+
+    /// The amount of the filled quantity whose traded funds have already been paid out to the user via claim_filled.
+    #[scrypto(mutable)]
+    pub quantity_claimed: Decimal,
+
+    /// The fee charged to the maker on the traded amount, net of any rebate (see FeeSchedule::net_maker_fee).
+    /// This is fixed when the order is created.
+    pub maker_fee: Decimal,
+
+    /// The policy that determines how long the order is kept in the order book
+    pub time_in_force: TimeInForce,
+
+    /// The trigger of a trigger order (stop-loss/take-profit) or None for a regular limit order.
+    /// Trigger orders do not live in the order book. Once their trigger fires, they are executed as market orders.
+    pub trigger: Option<Trigger>,
+
+    /// Whether the trigger of this order has fired. Always false for regular limit orders.
+    #[scrypto(mutable)]
+    pub triggered: bool,
+
+    /// The amount of the traded resource that a triggered order has received when it was executed as a market order.
+    /// Always zero for regular limit orders, as their traded amount follows from their price.
+    #[scrypto(mutable)]
+    pub amount_received: Decimal,
 }
 
 impl LimitOrder {
-    /// Creates a new limit order
+    /// Creates a new limit order that is charged the given net maker fee once it is filled
+    /// and that is kept in the order book according to the given time in force
     /// Panics if price or quantity are <= 0
     pub fn new(
         order_key: NonFungibleId,
         side: Side,
         price: Decimal,
         quantity: Decimal,
+        maker_fee: Decimal,
+        time_in_force: TimeInForce,
     ) -> LimitOrder {
         assert!(price.is_positive(), "Parameter price must be > zero");
         assert!(quantity.is_positive(), "Parameter quantity must be > zero");
@@ -61,9 +197,52 @@ impl LimitOrder {
             price,
             quantity,
             quantity_filled: Decimal::zero(),
+            quantity_claimed: Decimal::zero(),
+            maker_fee,
+            time_in_force,
+            trigger: None,
+            triggered: false,
+            amount_received: Decimal::zero(),
         }
     }
 
+    /// Creates a new trigger order. The trigger price doubles as the order price.
+    /// Trigger orders are executed as market orders and are therefore never charged a maker fee.
+    /// Once executed, any unfilled remainder is refunded, as for an immediate-or-cancel order.
+    /// Panics if the trigger price or quantity are <= 0
+    pub fn new_trigger(
+        order_key: NonFungibleId,
+        side: Side,
+        quantity: Decimal,
+        trigger: Trigger,
+    ) -> LimitOrder {
+        let mut order = LimitOrder::new(
+            order_key,
+            side,
+            trigger.price,
+            quantity,
+            Decimal::zero(),
+            TimeInForce::ImmediateOrCancel,
+        );
+        order.trigger = Some(trigger);
+        order
+    }
+
+    /// Records the execution of a triggered order as a market order.
+    /// The given quantity is the amount of the provided resource that has been traded and
+    /// amount_received is the amount of the traded resource that has been received in exchange.
+    pub fn record_trigger_execution(&mut self, quantity_filled: Decimal, amount_received: Decimal) {
+        assert!(self.trigger.is_some(), "Order is not a trigger order");
+        assert!(!self.triggered, "Order has already been triggered");
+        assert!(
+            quantity_filled <= self.quantity,
+            "The fill quantity is too high"
+        );
+        self.triggered = true;
+        self.quantity_filled = quantity_filled;
+        self.amount_received = amount_received;
+    }
+
     /// Fill the market order with the given quantity
     /// Panics if the given quantity would "overfill" the order
     pub fn fill(&mut self, quantity: Decimal) {
@@ -74,18 +253,95 @@ impl LimitOrder {
         self.quantity_filled += quantity;
     }
 
+    /// Amends the order to the given price and the given unfilled quantity.
+    /// Any filled quantity must have been claimed before, see claim.
+    /// If the price stays the same and the unfilled quantity is not increased, the order keeps its fill history and
+    /// its time priority. Otherwise, the order starts over as if it had been newly created with the given price and quantity.
+    ///
+    /// Returns true if the order keeps its time priority, else false.
+    /// Panics if price or quantity are <= 0
+    pub fn amend(&mut self, price: Decimal, unfilled_quantity: Decimal) -> bool {
+        assert!(price.is_positive(), "Parameter price must be > zero");
+        assert!(
+            unfilled_quantity.is_positive(),
+            "Parameter quantity must be > zero"
+        );
+        assert!(
+            self.quantity_claimed == self.quantity_filled,
+            "The filled quantity must be claimed before amending the order"
+        );
+
+        let keeps_priority =
+            price == self.price && unfilled_quantity <= self.quantity - self.quantity_filled;
+        if keeps_priority {
+            self.quantity = self.quantity_filled + unfilled_quantity;
+        } else {
+            self.price = price;
+            self.quantity = unfilled_quantity;
+            self.quantity_filled = Decimal::zero();
+            self.quantity_claimed = Decimal::zero();
+        }
+        keeps_priority
+    }
+
     /// Calculate the amounts of resources the user will receive upon closing the order.
     /// The first value returned is the amount the user will be refunded in case the order has not been filled fully.
     /// The second value returned is the amount that the user has successfully traded/received when the order was filled/partially filled.
+    /// The traded amount is net of the maker fee and does not include any amounts that have already been claimed.
     pub fn calculate_close_amounts(&self) -> (Decimal, Decimal) {
         let refund_amount = self.quantity - self.quantity_filled;
-        let traded_amount = match self.side {
-            Side::Ask => self.quantity_filled * self.price,
-            Side::Bid => self.quantity_filled / self.price,
-        };
+        let traded_amount = self.calculate_claim_amount();
 
         (refund_amount, traded_amount)
     }
+
+    /// Calculate the amount the user has traded/received for the filled quantity that has not been claimed yet.
+    /// The amount is net of the maker fee.
+    pub fn calculate_claim_amount(&self) -> Decimal {
+        let unclaimed_quantity = self.quantity_filled - self.quantity_claimed;
+        match (&self.trigger, self.side) {
+            // Triggered orders are filled all at once, so their unclaimed quantity is either nothing or everything
+            (Some(_), _) if unclaimed_quantity.is_positive() => self.amount_received,
+            (Some(_), _) => Decimal::zero(),
+            (None, Side::Ask) => {
+                unclaimed_quantity * self.price * (Decimal::one() - self.maker_fee)
+            }
+            (None, Side::Bid) => {
+                unclaimed_quantity / self.price * (Decimal::one() - self.maker_fee)
+            }
+        }
+    }
+
+    /// Marks the filled quantity as claimed and returns the amount the user has to be paid for it,
+    /// see calculate_claim_amount
+    pub fn claim(&mut self) -> Decimal {
+        let claim_amount = self.calculate_claim_amount();
+        self.quantity_claimed = self.quantity_filled;
+        claim_amount
+    }
+}
+
+/// Represents an aggregated price level of the order book, as returned by TradingPair::get_depth
+#[derive(sbor::TypeId, sbor::Encode, sbor::Decode, sbor::Describe, Clone, Debug)]
+pub struct PriceLevel {
+    /// The price of the price level
+    pub price: Decimal,
+
+    /// The total unfilled quantity of all orders at this price level, always denominated in the base resource
+    pub quantity: Decimal,
+
+    /// The number of orders at this price level
+    pub order_count: u32,
+}
+
+/// Represents a snapshot of the best price levels on both sides of the order book
+#[derive(sbor::TypeId, sbor::Encode, sbor::Decode, sbor::Describe, Clone, Debug)]
+pub struct OrderBookDepth {
+    /// The best price levels on the Bid side, starting with the highest price
+    pub bids: Vec<PriceLevel>,
+
+    /// The best price levels on the Ask side, starting with the lowest price
+    pub asks: Vec<PriceLevel>,
 }
 
 /// Represents one of the sides of an order book.
@@ -97,6 +353,9 @@ struct OrderBookSide {
     /// The orders on this side of the order book
     /// Keys represent the price level while values are vectors holding the keys of all orders that live on this price level.
     orders: BTreeMap<Decimal, Vec<NonFungibleId>>,
+
+    /// The last epoch in which an order is valid, for all orders on this side of the order book that expire
+    expiry_epochs: BTreeMap<NonFungibleId, u64>,
 }
 
 impl OrderBookSide {
@@ -104,30 +363,71 @@ impl OrderBookSide {
         Self {
             side,
             orders: BTreeMap::new(),
+            expiry_epochs: BTreeMap::new(),
         }
     }
 
-    /// Retrieves the best price level that exists on this side of the order book. If no orders exist, None is returned.
-    /// The returned tuple contains 1) the price level and 2) all orders at this price level.
-    fn get_best_price_level(&self) -> Option<(&Decimal, &Vec<NonFungibleId>)> {
-        // Implementation is likely inefficient and should be replaced with a better solution before production.
-        // Once https://github.com/rust-lang/rust/issues/62924 is resolved, switch to the appropriate methods.
+    /// Returns an iterator over all price levels on this side of the order book, starting with the best one.
+    /// Each item contains 1) the price level and 2) all orders at this price level.
+    fn price_levels(&self) -> Box<dyn Iterator<Item = (&Decimal, &Vec<NonFungibleId>)> + '_> {
         match self.side {
-            Side::Ask => self.orders.iter().next(),
-            Side::Bid => self.orders.iter().last(),
+            Side::Ask => Box::new(self.orders.iter()),
+            Side::Bid => Box::new(self.orders.iter().rev()),
         }
     }
 
+    /// Returns true if the order with the given key has not expired in the given epoch, else false
+    fn is_live(&self, order_key: &NonFungibleId, epoch: u64) -> bool {
+        self.expiry_epochs
+            .get(order_key)
+            .map_or(true, |expiry_epoch| epoch <= *expiry_epoch)
+    }
+
+    /// Retrieves the best order that has not expired in the given epoch. If no such order exists, None is returned.
+    /// The returned tuple contains 1) the price of the order and 2) the key of the order.
+    fn get_best_live_order(&self, epoch: u64) -> Option<(Decimal, &NonFungibleId)> {
+        self.price_levels().find_map(|(price, orders)| {
+            orders
+                .iter()
+                .find(|order_key| self.is_live(order_key, epoch))
+                .map(|order_key| (*price, order_key))
+        })
+    }
+
     /// Returns the key of the best order that exists on this side of the order book. Returns None if no order exists.
-    fn get_best_order(&self) -> Option<&NonFungibleId> {
-        let (_, best_orders) = self.get_best_price_level()?;
-        best_orders.first()
+    /// Orders that have expired in the given epoch are skipped.
+    fn get_best_order(&self, epoch: u64) -> Option<&NonFungibleId> {
+        let (_, order_key) = self.get_best_live_order(epoch)?;
+        Some(order_key)
     }
 
     /// Returns the price of the best order that exists on this side of the order book. Returns None if no order exists.
-    fn get_best_price(&self) -> Option<Decimal> {
-        let (price, _) = self.get_best_price_level()?;
-        Some(*price)
+    /// Orders that have expired in the given epoch are skipped.
+    fn get_best_price(&self, epoch: u64) -> Option<Decimal> {
+        let (price, _) = self.get_best_live_order(epoch)?;
+        Some(price)
+    }
+
+    /// Returns up to the given number of best price levels on this side of the order book, starting with the best one.
+    /// Each item contains 1) the price level and 2) the keys of all orders at this price level that have not expired
+    /// in the given epoch. Price levels without such orders are skipped.
+    fn get_live_price_levels(
+        &self,
+        epoch: u64,
+        levels: usize,
+    ) -> Vec<(Decimal, Vec<NonFungibleId>)> {
+        self.price_levels()
+            .map(|(price, orders)| {
+                let live_orders: Vec<NonFungibleId> = orders
+                    .iter()
+                    .filter(|order_key| self.is_live(order_key, epoch))
+                    .cloned()
+                    .collect();
+                (*price, live_orders)
+            })
+            .filter(|(_, live_orders)| !live_orders.is_empty())
+            .take(levels)
+            .collect()
     }
 
     /// Inserts the given limit order into this side of the order book
@@ -139,6 +439,10 @@ impl OrderBookSide {
                     .insert(order.price, vec![order.order_key.clone()]);
             }
         }
+        if let TimeInForce::GoodTillEpoch(expiry_epoch) = order.time_in_force {
+            self.expiry_epochs
+                .insert(order.order_key.clone(), expiry_epoch);
+        }
     }
 
     /// Removes the given order form this side of the order book
@@ -148,6 +452,7 @@ impl OrderBookSide {
         if price_level.is_empty() {
             self.orders.remove(&to_remove.price);
         }
+        self.expiry_epochs.remove(&to_remove.order_key);
     }
 }
 
@@ -171,21 +476,48 @@ impl OrderBook {
     }
 
     /// Returns the key of the best order in the order book for the given side. Returns None if no orders exists
-    /// on that side of the order book.
-    pub fn get_best_order(&self, side: Side) -> Option<&NonFungibleId> {
+    /// on that side of the order book. Orders that have expired in the given epoch are skipped.
+    pub fn get_best_order(&self, side: Side, epoch: u64) -> Option<&NonFungibleId> {
+        let side = match side {
+            Side::Ask => &self.asks,
+            Side::Bid => &self.bids,
+        };
+        side.get_best_order(epoch)
+    }
+
+    /// Returns the price of the best order in the order book for the given side. Returns None if no orders exists
+    /// on that side of the order book. Orders that have expired in the given epoch are skipped.
+    pub fn get_best_price(&self, side: Side, epoch: u64) -> Option<Decimal> {
+        let side = match side {
+            Side::Ask => &self.asks,
+            Side::Bid => &self.bids,
+        };
+        side.get_best_price(epoch)
+    }
+
+    /// Returns up to the given number of best price levels for the given side, starting with the best one.
+    /// Each item contains 1) the price level and 2) the keys of all orders at this price level.
+    /// Orders that have expired in the given epoch are skipped.
+    pub fn get_price_levels(
+        &self,
+        side: Side,
+        epoch: u64,
+        levels: usize,
+    ) -> Vec<(Decimal, Vec<NonFungibleId>)> {
         let side = match side {
             Side::Ask => &self.asks,
             Side::Bid => &self.bids,
         };
-        side.get_best_order()
+        side.get_live_price_levels(epoch, levels)
     }
 
     /// Inserts a limit order into the given side of the order book.
-    /// Panics if the order is priced such that it would constitute a market order.
-    pub fn insert_limit_order(&mut self, order: &LimitOrder) {
+    /// Panics if the order is priced such that it would constitute a market order, given the orders that have
+    /// not expired in the given epoch.
+    pub fn insert_limit_order(&mut self, order: &LimitOrder, epoch: u64) {
         match order.side {
             Side::Ask => {
-                let best_bid_price = self.bids.get_best_price();
+                let best_bid_price = self.bids.get_best_price(epoch);
                 assert!(
                     best_bid_price.is_none() || order.price > best_bid_price.unwrap(),
                     "Order would be a market order"
@@ -193,7 +525,7 @@ impl OrderBook {
                 self.asks.insert_limit_order(order);
             }
             Side::Bid => {
-                let best_ask_price = self.asks.get_best_price();
+                let best_ask_price = self.asks.get_best_price(epoch);
                 assert!(
                     best_ask_price.is_none() || order.price < best_ask_price.unwrap(),
                     "Order would be a market order"
@@ -225,6 +557,8 @@ mod test {
             Side::Ask,
             0.into(),
             1.into(),
+            0.into(),
+            TimeInForce::PostOnly,
         );
     }
 
@@ -236,6 +570,8 @@ mod test {
             Side::Ask,
             (-1).into(),
             1.into(),
+            0.into(),
+            TimeInForce::PostOnly,
         );
     }
 
@@ -247,6 +583,8 @@ mod test {
             Side::Ask,
             1.into(),
             0.into(),
+            0.into(),
+            TimeInForce::PostOnly,
         );
     }
 
@@ -258,6 +596,227 @@ mod test {
             Side::Ask,
             1.into(),
             (-1).into(),
+            0.into(),
+            TimeInForce::PostOnly,
+        );
+    }
+
+    #[test]
+    fn test_trigger_is_met() {
+        let above = Trigger {
+            price: 10.into(),
+            direction: TriggerDirection::Above,
+        };
+        assert!(!above.is_met(9.into()));
+        assert!(above.is_met(10.into()));
+        assert!(above.is_met(11.into()));
+
+        let below = Trigger {
+            price: 10.into(),
+            direction: TriggerDirection::Below,
+        };
+        assert!(below.is_met(9.into()));
+        assert!(below.is_met(10.into()));
+        assert!(!below.is_met(11.into()));
+    }
+
+    #[test]
+    fn test_trigger_order_close_amounts() {
+        let mut order = LimitOrder::new_trigger(
+            NonFungibleId::from_str("1234").unwrap(),
+            Side::Ask,
+            100.into(),
+            Trigger {
+                price: 10.into(),
+                direction: TriggerDirection::Below,
+            },
+        );
+        let (refund_amount, traded_amount) = order.calculate_close_amounts();
+        assert_eq!(refund_amount, 100.into());
+        assert_eq!(traded_amount, 0.into());
+
+        order.record_trigger_execution(80.into(), 750.into());
+        let (refund_amount, traded_amount) = order.calculate_close_amounts();
+        assert_eq!(refund_amount, 20.into());
+        assert_eq!(traded_amount, 750.into());
+    }
+
+    #[test]
+    fn test_limit_order_close_amounts_are_net_of_maker_fee() {
+        let mut order = LimitOrder::new(
+            NonFungibleId::from_str("1234").unwrap(),
+            Side::Ask,
+            10.into(),
+            100.into(),
+            "0.01".into(),
+            TimeInForce::PostOnly,
+        );
+        order.fill(50.into());
+        let (refund_amount, traded_amount) = order.calculate_close_amounts();
+        assert_eq!(refund_amount, 50.into());
+        assert_eq!(traded_amount, 495.into());
+    }
+
+    #[test]
+    fn test_good_till_epoch_expiry() {
+        let time_in_force = TimeInForce::GoodTillEpoch(10);
+        assert!(!time_in_force.is_expired(9));
+        assert!(!time_in_force.is_expired(10));
+        assert!(time_in_force.is_expired(11));
+        assert!(!TimeInForce::PostOnly.is_expired(u64::MAX));
+    }
+
+    #[test]
+    fn test_order_book_skips_expired_orders() {
+        let expiring_order = LimitOrder::new(
+            NonFungibleId::from_str("1234").unwrap(),
+            Side::Bid,
+            11.into(),
+            100.into(),
+            0.into(),
+            TimeInForce::GoodTillEpoch(10),
         );
+        let order = LimitOrder::new(
+            NonFungibleId::from_str("5678").unwrap(),
+            Side::Bid,
+            10.into(),
+            100.into(),
+            0.into(),
+            TimeInForce::PostOnly,
+        );
+        let mut order_book = OrderBook::new();
+        order_book.insert_limit_order(&expiring_order, 5);
+        order_book.insert_limit_order(&order, 5);
+
+        assert_eq!(
+            order_book.get_best_order(Side::Bid, 10).unwrap().to_vec(),
+            expiring_order.order_key.to_vec()
+        );
+        assert_eq!(
+            order_book.get_best_order(Side::Bid, 11).unwrap().to_vec(),
+            order.order_key.to_vec()
+        );
+
+        order_book.remove_limit_order(&order);
+        assert!(order_book.get_best_order(Side::Bid, 11).is_none());
+    }
+
+    #[test]
+    fn test_order_book_price_levels() {
+        let mut order_book = OrderBook::new();
+        for (key, price, time_in_force) in [
+            ("01", 10, TimeInForce::PostOnly),
+            ("02", 12, TimeInForce::PostOnly),
+            ("03", 10, TimeInForce::PostOnly),
+            ("04", 11, TimeInForce::GoodTillEpoch(10)),
+        ] {
+            let order = LimitOrder::new(
+                NonFungibleId::from_str(key).unwrap(),
+                Side::Ask,
+                price.into(),
+                100.into(),
+                0.into(),
+                time_in_force,
+            );
+            order_book.insert_limit_order(&order, 5);
+        }
+
+        let levels = order_book.get_price_levels(Side::Ask, 5, 2);
+        assert_eq!(levels.len(), 2);
+        assert_eq!(levels[0].0, 10.into());
+        assert_eq!(levels[0].1.len(), 2);
+        assert_eq!(levels[1].0, 11.into());
+
+        // The expired order's price level is skipped
+        let levels = order_book.get_price_levels(Side::Ask, 11, 2);
+        assert_eq!(levels.len(), 2);
+        assert_eq!(levels[1].0, 12.into());
+
+        assert_eq!(order_book.get_best_price(Side::Ask, 11), Some(10.into()));
+        assert_eq!(order_book.get_best_price(Side::Bid, 11), None);
+    }
+
+    #[test]
+    fn test_limit_order_claim() {
+        let mut order = LimitOrder::new(
+            NonFungibleId::from_str("1234").unwrap(),
+            Side::Bid,
+            10.into(),
+            1000.into(),
+            0.into(),
+            TimeInForce::PostOnly,
+        );
+        order.fill(200.into());
+        assert_eq!(order.claim(), 20.into());
+        assert_eq!(order.claim(), 0.into());
+
+        order.fill(300.into());
+        let (refund_amount, traded_amount) = order.calculate_close_amounts();
+        assert_eq!(refund_amount, 500.into());
+        assert_eq!(traded_amount, 30.into());
+    }
+
+    #[test]
+    fn test_limit_order_amend() {
+        let mut order = LimitOrder::new(
+            NonFungibleId::from_str("1234").unwrap(),
+            Side::Ask,
+            10.into(),
+            1000.into(),
+            0.into(),
+            TimeInForce::PostOnly,
+        );
+        order.fill(200.into());
+        order.claim();
+
+        // Lowering the quantity at the same price keeps the fill history
+        assert!(order.amend(10.into(), 500.into()));
+        assert_eq!(order.quantity, 700.into());
+        assert_eq!(order.quantity_filled, 200.into());
+
+        // Increasing the quantity starts over
+        assert!(!order.amend(10.into(), 600.into()));
+        assert_eq!(order.quantity, 600.into());
+        assert_eq!(order.quantity_filled, 0.into());
+
+        // Changing the price starts over
+        assert!(!order.amend(11.into(), 600.into()));
+        assert_eq!(order.price, 11.into());
+        let (refund_amount, traded_amount) = order.calculate_close_amounts();
+        assert_eq!(refund_amount, 600.into());
+        assert_eq!(traded_amount, 0.into());
+    }
+
+    #[test]
+    #[should_panic(expected = "The filled quantity must be claimed before amending the order")]
+    fn test_limit_order_amend_panics_on_unclaimed_fills() {
+        let mut order = LimitOrder::new(
+            NonFungibleId::from_str("1234").unwrap(),
+            Side::Ask,
+            10.into(),
+            1000.into(),
+            0.into(),
+            TimeInForce::PostOnly,
+        );
+        order.fill(200.into());
+        order.amend(10.into(), 500.into());
+    }
+
+    #[test]
+    fn test_fee_schedule_net_maker_fee() {
+        let fee_schedule = FeeSchedule::new("0.001".into(), "0.002".into(), "0.0015".into());
+        assert_eq!(fee_schedule.net_maker_fee(), "-0.0005".into());
+    }
+
+    #[test]
+    #[should_panic(expected = "Parameter maker_rebate must not be higher than taker_fee")]
+    fn test_fee_schedule_panics_on_rebate_higher_than_taker_fee() {
+        FeeSchedule::new(0.into(), "0.001".into(), "0.002".into());
+    }
+
+    #[test]
+    #[should_panic(expected = "Parameter taker_fee must be >= zero and < one")]
+    fn test_fee_schedule_panics_on_negative_fee() {
+        FeeSchedule::new(0.into(), "-0.001".into(), 0.into());
     }
 }
@@ -23,12 +23,34 @@ blueprint! {
 
         /// The resources that market makers have deposited into this component by creating limit bid orders
         quote_funds: Vault,
+
+        /// The fees charged by this trading pair
+        fee_schedule: FeeSchedule,
+
+        /// The fees that have been collected in the base resource
+        base_fees: Vault,
+
+        /// The fees that have been collected in the quote resource
+        quote_fees: Vault,
+
+        /// The price at which the last market order has been filled or None if no trade has happened yet
+        last_price: Option<Decimal>,
+
+        /// The keys of all trigger orders whose trigger has not fired yet, in the order they have been created.
+        /// Trigger orders do not live in the order book until they fire.
+        trigger_orders: Vec<NonFungibleId>,
     }
 
     impl TradingPair {
         /// Instantiates a new TradingPair component for the given base_resource and quote_resource.
-        /// To create a trading pair XRD/rUSD, one would issue a call like this: `instantiate(xrd_address, rusd_address)`
-        pub fn instantiate(base_resource: ResourceAddress, quote_resource: ResourceAddress) -> ComponentAddress {
+        /// To create a trading pair XRD/rUSD, one would issue a call like this: `instantiate(xrd_address, rusd_address, ...)`
+        /// The collected fees can be withdrawn by whoever holds the given admin_badge.
+        pub fn instantiate(
+            base_resource: ResourceAddress,
+            quote_resource: ResourceAddress,
+            fee_schedule: FeeSchedule,
+            admin_badge: ResourceAddress,
+        ) -> ComponentAddress {
             assert_is_fungible(&base_resource);
             assert_is_fungible(&quote_resource);
 
@@ -51,14 +73,25 @@ blueprint! {
                 .updateable_non_fungible_data(rule!(require(minter.resource_address())), LOCKED)
                 .no_initial_supply();
 
-            Self {
+            let component = Self {
                 minter: Vault::with_bucket(minter),
                 order_resource,
                 order_book: OrderBook::new(),
                 base_funds: Vault::new(base_resource),
                 quote_funds: Vault::new(quote_resource),
+                fee_schedule,
+                base_fees: Vault::new(base_resource),
+                quote_fees: Vault::new(quote_resource),
+                last_price: None,
+                trigger_orders: Vec::new(),
             }
-            .instantiate().globalize()
+            .instantiate();
+
+            let access_rules = AccessRules::new()
+                .method("withdraw_fees", rule!(require(admin_badge)))
+                .default(rule!(allow_all));
+
+            component.add_access_check(access_rules).globalize()
         }
 
         /// Creates a new limit order. The side of the order (Ask/Bid) is derived form the given funds bucket.
@@ -69,27 +102,114 @@ blueprint! {
         /// Returns a bucket with a NFR that represents the order. This NFR can be used to track the order
         /// and to cancel it or redeem the traded resources.
         ///
+        /// The order is created as a post-only order, see `new_limit_order_with_time_in_force`.
+        ///
         /// Panics if the order would be a market order. Never executes directly against existing market orders.
         /// Panics if price is <= 0
         pub fn new_limit_order(&mut self, funds: Bucket, price: Decimal) -> Bucket {
-            // Determine the side of the order
+            self.rest_limit_order(funds, price, TimeInForce::PostOnly)
+        }
+
+        /// Creates a new limit order with the given time in force. The side and price of the order are interpreted
+        /// exactly like for `new_limit_order`.
+        ///
+        /// Unless the order is post-only, it is first matched against existing limit orders, like a market order, but only
+        /// as far as its price allows. The taker fee applies to this part. What happens to the rest depends on the time in force:
+        /// - PostOnly: the order is never matched and rests in the order book. Panics if the order would be a market order.
+        /// - ImmediateOrCancel: the rest is refunded.
+        /// - FillOrKill: panics if the order cannot be matched completely.
+        /// - GoodTillEpoch: the rest rests in the order book until the given epoch has passed.
+        ///
+        /// Returns three values:
+        /// 1. A bucket with a NFR representing the order if some of it rests in the order book, else None
+        /// 2. The funds that have been refunded, which may be empty
+        /// 3. The traded funds, if the order has been matched against existing limit orders
+        ///
+        /// Panics if price is <= 0
+        /// Panics if a good-till-epoch order would already have expired
+        pub fn new_limit_order_with_time_in_force(
+            &mut self,
+            mut funds: Bucket,
+            price: Decimal,
+            time_in_force: TimeInForce,
+        ) -> (Option<Bucket>, Bucket, Option<Bucket>) {
+            assert!(price.is_positive(), "Parameter price must be > zero");
+            assert!(
+                !time_in_force.is_expired(Runtime::current_epoch()),
+                "Order would already have expired"
+            );
+
+            // Match the order against the order book, unless it is post-only
+            let traded_funds = match time_in_force {
+                TimeInForce::PostOnly => None,
+                _ => {
+                    let (unmatched_funds, traded_funds) = self.match_market_order(funds, Some(price), true);
+                    funds = unmatched_funds;
+                    traded_funds
+                }
+            };
+
+            // Rest or refund the funds that could not be matched
+            let order = match time_in_force {
+                TimeInForce::PostOnly => Some(self.rest_limit_order(funds.take(funds.amount()), price, time_in_force)),
+                TimeInForce::GoodTillEpoch(_) if !is_almost_zero(funds.amount(), price) => {
+                    Some(self.rest_limit_order(funds.take(funds.amount()), price, time_in_force))
+                }
+                TimeInForce::FillOrKill => {
+                    assert!(
+                        is_almost_zero(funds.amount(), price),
+                        "Fill-or-kill order could not be filled completely"
+                    );
+                    None
+                }
+                _ => None,
+            };
+
+            // Matching the order may have moved the price far enough to fire some trigger orders
+            if traded_funds.is_some() {
+                self.execute_triggered_orders();
+            }
+
+            (order, funds, traded_funds)
+        }
+
+        /// Creates a new trigger order (stop-loss/take-profit). The side of the order is derived from the given funds bucket,
+        /// exactly like for limit orders. The order does not live in the order book. Instead, once the last traded price
+        /// crosses the given trigger_price in the given direction, the order is executed as a market order.
+        /// As with limit orders, the trigger price is the amount of the quote resource paid for one unit of the base resource.
+        ///
+        /// A triggered order is filled as far as the order book allows. Any unfilled remainder as well as the traded funds
+        /// can be redeemed via `close_limit_order`, which also refunds trigger orders that have not fired yet.
+        ///
+        /// Returns a bucket with a NFR that represents the order.
+        ///
+        /// Panics if the trigger condition is already met by the last traded price.
+        /// Panics if trigger_price is <= 0
+        pub fn new_trigger_order(
+            &mut self,
+            funds: Bucket,
+            trigger_price: Decimal,
+            direction: TriggerDirection,
+        ) -> Bucket {
             let side = self.get_order_side(&funds);
-            // Generate a new random order key
             let order_key = NonFungibleId::random();
-            // Create a new limit order object. This will check that the price is not <= 0
-            let order = LimitOrder::new(order_key.clone(), side, price, funds.amount());
-            // Insert the limit order into the order book. This panics if the order would be a market order.
-            // The order book will only store a reference to the order (the order key). The order NFR will be given to the
-            // user at the end of this method.
-            self.order_book.insert_limit_order(&order);
+            let trigger = Trigger {
+                price: trigger_price,
+                direction,
+            };
+            // Refuse triggers that would fire immediately, the user should create a market order instead
+            assert!(
+                self.last_price.is_none() || !trigger.is_met(self.last_price.unwrap()),
+                "Trigger condition is already met by the last price"
+            );
+            let order = LimitOrder::new_trigger(order_key.clone(), side, funds.amount(), trigger);
+            self.trigger_orders.push(order_key.clone());
 
-            // Store the user supplied funds in the correct vault
             match side {
                 Side::Ask => self.base_funds.put(funds),
                 Side::Bid => self.quote_funds.put(funds),
             }
 
-            // Mint a new NFR representing the order and give it to the user
             self.minter.authorize(|| {
                 borrow_resource_manager!(self.order_resource)
                     .mint_non_fungible(&order_key, order)
@@ -111,9 +231,16 @@ blueprint! {
             let order: LimitOrder =
                 order_bucket.non_fungible::<LimitOrder>().data();
 
-            // If the order has not been filled completely, it still is referenced in the order book so we have to remove it.
+            // Trigger orders that have not fired yet are waiting outside the order book and simply have to be forgotten.
+            // Triggered orders have been executed as market orders and are not referenced anywhere.
+            // If a limit order has not been filled completely, it still is referenced in the order book so we have to remove it.
             // If the order has already been filled completely, it will already have been removed from the order book.
-            if order.quantity_filled < order.quantity {
+            if order.trigger.is_some() {
+                if !order.triggered {
+                    self.trigger_orders
+                        .retain(|order_key| order_key.to_vec() != order.order_key.to_vec());
+                }
+            } else if order.quantity_filled < order.quantity {
                 self.order_book.remove_limit_order(&order);
             }
             // Burn the order NFR. It is no longer needed as the order will no longer exist after this method finishes.
@@ -137,11 +264,132 @@ blueprint! {
             }
         }
 
+        /// Pays out the resources that the given order has traded/received so far, without closing the order.
+        /// The order stays in the order book and can be filled further. Only the filled quantity that has not
+        /// been claimed before is paid out, net of the maker fee.
+        /// The order is identified by a proof of its NFR, so the NFR stays with the user.
+        ///
+        /// Returns a bucket with the traded resources, which may be empty.
+        pub fn claim_filled(&mut self, order_proof: Proof) -> Bucket {
+            // Make sure the given proof does indeed contain an order NFR
+            assert_eq!(
+                order_proof.resource_address(),
+                self.order_resource,
+                "Invalid resource supplied: proof does not contain an order"
+            );
+            // Load the data belonging to the order NFR
+            let order_key = order_proof.non_fungible::<LimitOrder>().id();
+            let mut order: LimitOrder = order_proof.non_fungible::<LimitOrder>().data();
+            order_proof.drop();
+
+            // Mark the filled quantity as claimed and persist that on the order NFR
+            let claim_amount = order.claim();
+            let side = order.side;
+            self.minter.authorize(|| {
+                borrow_resource_manager!(self.order_resource)
+                    .update_non_fungible_data(&order_key, order)
+            });
+
+            // The traded resources are the opposite of the resource the order has provided
+            match side {
+                Side::Ask => self.quote_funds.take(claim_amount),
+                Side::Bid => self.base_funds.take(claim_amount),
+            }
+        }
+
+        /// Amends the given limit order to the given price and the given unfilled quantity. The order is identified by a
+        /// proof of its NFR, so the order keeps its NFR. Before the order is amended, the resources it has traded/received
+        /// so far are paid out, like with `claim_filled`.
+        ///
+        /// If the price stays the same and the unfilled quantity is lowered, the order keeps its time priority.
+        /// Otherwise, it is moved to the end of the queue at its (new) price level.
+        /// If the unfilled quantity is increased, the difference is taken from the given funds, which must hold the
+        /// resource the order provides. If it is lowered, the difference is refunded.
+        ///
+        /// Returns two buckets with
+        /// 1. the refund, including any funds that have been supplied but were not needed
+        /// 2. the traded/received resources, which may be empty
+        ///
+        /// Panics if the order is a trigger order, has been filled completely or has expired.
+        /// Panics if the order would become a market order.
+        pub fn amend_limit_order(
+            &mut self,
+            order_proof: Proof,
+            price: Decimal,
+            quantity: Decimal,
+            funds: Option<Bucket>,
+        ) -> (Bucket, Bucket) {
+            // Make sure the given proof does indeed contain an order NFR
+            assert_eq!(
+                order_proof.resource_address(),
+                self.order_resource,
+                "Invalid resource supplied: proof does not contain an order"
+            );
+            // Load the data belonging to the order NFR
+            let order_key = order_proof.non_fungible::<LimitOrder>().id();
+            let mut order: LimitOrder = order_proof.non_fungible::<LimitOrder>().data();
+            order_proof.drop();
+
+            let epoch = Runtime::current_epoch();
+            assert!(order.trigger.is_none(), "Trigger orders cannot be amended");
+            assert!(
+                order.quantity_filled < order.quantity,
+                "The order has already been filled completely"
+            );
+            assert!(
+                !order.time_in_force.is_expired(epoch),
+                "The order has expired"
+            );
+
+            // Pay out what the order has traded so far, so that the fills are settled at the old price
+            let claim_amount = order.claim();
+            let old_unfilled_quantity = order.quantity - order.quantity_filled;
+
+            // If the order loses its priority, take it out of the order book at its old price level and put it back in
+            let old_order = order.clone();
+            if !order.amend(price, quantity) {
+                self.order_book.remove_limit_order(&old_order);
+                self.order_book.insert_limit_order(&order, epoch);
+            }
+
+            // Collect or refund the difference in the unfilled quantity
+            let (funds_vault, traded_vault) = match order.side {
+                Side::Ask => (&mut self.base_funds, &mut self.quote_funds),
+                Side::Bid => (&mut self.quote_funds, &mut self.base_funds),
+            };
+            let traded = traded_vault.take(claim_amount);
+            let mut refund = funds_vault.take(Decimal::zero());
+            if quantity > old_unfilled_quantity {
+                let mut funds = funds.expect("Insufficient funds: the order quantity has been increased");
+                assert_eq!(
+                    funds.resource_address(),
+                    funds_vault.resource_address(),
+                    "The supplied bucket contains an invalid resource"
+                );
+                funds_vault.put(funds.take(quantity - old_unfilled_quantity));
+                refund.put(funds);
+            } else {
+                refund.put(funds_vault.take(old_unfilled_quantity - quantity));
+                if let Some(funds) = funds {
+                    refund.put(funds);
+                }
+            }
+
+            self.minter.authorize(|| {
+                borrow_resource_manager!(self.order_resource)
+                    .update_non_fungible_data(&order_key, order)
+            });
+
+            (refund, traded)
+        }
+
         /// Creates a new market order that is executed directly against existing limit orders.
         /// The side of the order is derived from the given funds bucket.
         /// If the base resource is supplied in that bucket, an Ask order is inferred, if the quote resource is supplied,
         /// a Bid order is inferred.
         ///
+        /// The taker fee is charged on top of the funds that are exchanged, i.e. it is taken out of the supplied funds bucket.
+        ///
         /// A user calling this method should place an assertion in their transaction manifest, enforcing that at least
         /// some minimum amount of the traded resource is received. This is especially important if the user places a
         /// large order and "rides down" the order book multiple price levels.
@@ -150,8 +398,75 @@ blueprint! {
         /// 1. Any dust that might still exist in the supplied funds bucket after executing the market order.
         /// 2. The traded funds that are received in exchange for the supplied funds.
         ///
+        /// Trigger orders whose trigger is met after the market order has been filled are executed right away.
+        ///
         /// Panics if the order cannot be filled by existing limit orders.
-        pub fn new_market_order(&mut self, mut funds: Bucket) -> (Bucket, Option<Bucket>) {
+        pub fn new_market_order(&mut self, funds: Bucket) -> (Bucket, Option<Bucket>) {
+            let (funds, funds_to_return) = self.match_market_order(funds, None, false);
+
+            // The market order may have moved the price far enough to fire some trigger orders
+            self.execute_triggered_orders();
+
+            // Finally return to the user 1) the unspent funds of the market order
+            // and 2) the traded funds coming from the limit order(s)
+            (funds, funds_to_return)
+        }
+
+        /// Returns the fees charged by this trading pair
+        pub fn get_fee_schedule(&self) -> FeeSchedule {
+            self.fee_schedule
+        }
+
+        /// Withdraws all fees that have been collected so far.
+        /// Returns two buckets with 1) the fees collected in the base resource and 2) the fees collected in the quote resource.
+        /// This method requires the Dex admin badge.
+        pub fn withdraw_fees(&mut self) -> (Bucket, Bucket) {
+            (self.base_fees.take_all(), self.quote_fees.take_all())
+        }
+
+        /// Returns the price of the best (highest) Bid order or None if there are no Bid orders
+        pub fn get_best_bid(&self) -> Option<Decimal> {
+            self.order_book.get_best_price(Side::Bid, Runtime::current_epoch())
+        }
+
+        /// Returns the price of the best (lowest) Ask order or None if there are no Ask orders
+        pub fn get_best_ask(&self) -> Option<Decimal> {
+            self.order_book.get_best_price(Side::Ask, Runtime::current_epoch())
+        }
+
+        /// Returns the difference between the best Ask price and the best Bid price
+        /// or None if either side of the order book is empty
+        pub fn get_spread(&self) -> Option<Decimal> {
+            Some(self.get_best_ask()? - self.get_best_bid()?)
+        }
+
+        /// Returns a snapshot of up to the given number of best price levels on each side of the order book.
+        /// For every price level, the total unfilled quantity (denominated in the base resource, irrespective of the side)
+        /// and the number of orders are aggregated. Expired orders are not included.
+        pub fn get_depth(&self, levels: u32) -> OrderBookDepth {
+            OrderBookDepth {
+                bids: self.get_price_levels(Side::Bid, levels),
+                asks: self.get_price_levels(Side::Ask, levels),
+            }
+        }
+
+        /// Returns the price at which the last market order has been filled or None if no trade has happened yet
+        pub fn get_last_price(&self) -> Option<Decimal> {
+            self.last_price
+        }
+
+        /// Executes the given market order funds against existing limit orders and records the last traded price.
+        /// Returns the unspent funds and the traded funds.
+        ///
+        /// If a limit_price is given, only limit orders priced at or better than it are matched.
+        /// If allow_partial_fill is false, panics if the order cannot be filled by existing limit orders.
+        /// Otherwise, the order is filled as far as the order book allows.
+        fn match_market_order(
+            &mut self,
+            mut funds: Bucket,
+            limit_price: Option<Decimal>,
+            allow_partial_fill: bool,
+        ) -> (Bucket, Option<Bucket>) {
             // Infer the side of the order
             let market_order_side = self.get_order_side(&funds);
             let limit_order_side = market_order_side.opposite();
@@ -164,25 +479,48 @@ blueprint! {
             // filling it. Stop when the funds of the market order are expended.
             // This may fail in a low liquidity situation where there are too few funds on the limit
             // order side to fill the market order.
+            let epoch = Runtime::current_epoch();
             let mut last_price = Decimal(1i128);
             while !is_almost_zero(funds.amount(), last_price) {
                 // Get the current best limit order from the order book (this only returns the order key).
                 // Panic if there are no more limit orders that can be matched to the market order
-                let limit_order_key = self.order_book.get_best_order(limit_order_side)
-                    .expect("Insufficient liquidity: no limit orders found that can be matched to the market order")
-                    .clone();
+                let limit_order_key = match self.order_book.get_best_order(limit_order_side, epoch) {
+                    Some(order_key) => order_key.clone(),
+                    None => {
+                        assert!(
+                            allow_partial_fill,
+                            "Insufficient liquidity: no limit orders found that can be matched to the market order"
+                        );
+                        break;
+                    }
+                };
 
                 // Using the order key, load the data for the limit order
                 let mut limit_order: LimitOrder =
                     borrow_resource_manager!(self.order_resource).get_non_fungible_data(&limit_order_key);
 
+                // Stop once the best limit order is priced worse than the limit price
+                if let Some(limit_price) = limit_price {
+                    let is_acceptable = match limit_order_side {
+                        Side::Ask => limit_order.price <= limit_price,
+                        Side::Bid => limit_order.price >= limit_price,
+                    };
+                    if !is_acceptable {
+                        break;
+                    }
+                }
+
                 // Save the limit order's price as the last known price
                 last_price = limit_order.price;
+                self.last_price = Some(last_price);
 
-                // Calculate what quantity of the limit order can be filled using the funds that remain in the market order
+                // Calculate what quantity of the limit order can be filled using the funds that remain in the market order.
+                // The taker fee is paid on top of the exchanged funds, so only part of the remaining funds can be exchanged.
+                let taker_fee = self.fee_schedule.taker_fee;
+                let exchangeable_funds = funds.amount() / (Decimal::one() + taker_fee);
                 let supplied_quantity = match limit_order_side {
-                    Side::Ask => funds.amount() / limit_order.price,
-                    Side::Bid => funds.amount() * limit_order.price,
+                    Side::Ask => exchangeable_funds / limit_order.price,
+                    Side::Bid => exchangeable_funds * limit_order.price,
                 };
 
                 // Also calculate the quantity of the limit order that has not been filled yet
@@ -195,19 +533,30 @@ blueprint! {
                 // Then, fill the limit order wit this quantity
                 limit_order.fill(fill_quantity);
 
+                // Calculate the amount of the market order funds that the maker receives and the fees that are charged on it.
+                // The maker rebate is paid out of the taker fee, which is why the fee vault never has to fund it.
+                let payment_amount = match limit_order_side {
+                    Side::Ask => fill_quantity * limit_order.price,
+                    Side::Bid => fill_quantity / limit_order.price,
+                };
+                let taker_fee_amount = payment_amount * taker_fee;
+                let maker_fee_amount = payment_amount * limit_order.maker_fee;
+
                 // Depending on the limit order side, take an appropriate amount of the market order funds and store them in this component.
-                // The market maker user will be able to claim them later. Also add the traded funds that the taker user will receive to the
-                // funds_to_return bucket.
-                
+                // The taker pays the exchanged amount plus the taker fee. The market maker user will be able to claim the
+                // exchanged amount later, net of the maker fee. The fees go to the fee vault.
+                // Also add the traded funds that the taker user will receive to the funds_to_return bucket.
+                let mut payment = funds.take(payment_amount + taker_fee_amount);
+                let fees = payment.take(taker_fee_amount + maker_fee_amount);
                 let bucket_to_add = match limit_order_side {
                     Side::Ask => {
-                        self.quote_funds
-                            .put(funds.take(fill_quantity * limit_order.price));
+                        self.quote_fees.put(fees);
+                        self.quote_funds.put(payment);
                         self.base_funds.take(fill_quantity)
                     }
                     Side::Bid => {
-                        self.base_funds
-                            .put(funds.take(fill_quantity / limit_order.price));
+                        self.base_fees.put(fees);
+                        self.base_funds.put(payment);
                         self.quote_funds.take(fill_quantity)
                     }
                 };
@@ -233,11 +582,133 @@ blueprint! {
                 });
             }
 
-            // Finally return to the user 1) the unspent funds of the market order
-            // and 2) the traded funds coming from the limit order(s)
             (funds, funds_to_return)
         }
 
+        /// Executes all trigger orders whose trigger is met by the last traded price as market orders.
+        /// As each execution moves the price, this repeats until no more triggers fire.
+        /// Triggered orders are filled as far as the order book allows, their funds remain in this component
+        /// until the owner closes the order.
+        fn execute_triggered_orders(&mut self) {
+            // Load the triggers of all pending orders once, in the same order as trigger_orders
+            let order_resource = self.order_resource;
+            let mut triggers: Vec<Trigger> = self
+                .trigger_orders
+                .iter()
+                .map(|order_key| {
+                    let order: LimitOrder =
+                        borrow_resource_manager!(order_resource).get_non_fungible_data(order_key);
+                    order.trigger.unwrap()
+                })
+                .collect();
+
+            loop {
+                let last_price = match self.last_price {
+                    Some(last_price) => last_price,
+                    None => return,
+                };
+                let triggered = triggers.iter().position(|trigger| trigger.is_met(last_price));
+                // Remove the fired order from both lists so they stay aligned
+                let order_key = match triggered {
+                    Some(index) => {
+                        triggers.remove(index);
+                        self.trigger_orders.remove(index)
+                    }
+                    None => return,
+                };
+
+                let mut order: LimitOrder =
+                    borrow_resource_manager!(self.order_resource).get_non_fungible_data(&order_key);
+                let funds = match order.side {
+                    Side::Ask => self.base_funds.take(order.quantity),
+                    Side::Bid => self.quote_funds.take(order.quantity),
+                };
+                let (unspent_funds, traded_funds) = self.match_market_order(funds, None, true);
+                let amount_received = traded_funds
+                    .as_ref()
+                    .map_or(Decimal::zero(), |bucket| bucket.amount());
+                let quantity_filled = order.quantity - unspent_funds.amount();
+                order.record_trigger_execution(quantity_filled, amount_received);
+
+                // Keep both the unspent and the traded funds for the owner of the order
+                match order.side {
+                    Side::Ask => self.base_funds.put(unspent_funds),
+                    Side::Bid => self.quote_funds.put(unspent_funds),
+                }
+                if let Some(traded_funds) = traded_funds {
+                    match order.side {
+                        Side::Ask => self.quote_funds.put(traded_funds),
+                        Side::Bid => self.base_funds.put(traded_funds),
+                    }
+                }
+
+                self.minter.authorize(|| {
+                    borrow_resource_manager!(self.order_resource)
+                        .update_non_fungible_data(&order_key, order)
+                });
+            }
+        }
+
+        /// Puts the given funds into the order book as a limit order with the given price and time in force.
+        /// Returns a bucket with a NFR that represents the order.
+        /// Panics if the order would be a market order.
+        fn rest_limit_order(&mut self, funds: Bucket, price: Decimal, time_in_force: TimeInForce) -> Bucket {
+            // Determine the side of the order
+            let side = self.get_order_side(&funds);
+            // Generate a new random order key
+            let order_key = NonFungibleId::random();
+            // Create a new limit order object. This will check that the price is not <= 0
+            let order = LimitOrder::new(
+                order_key.clone(),
+                side,
+                price,
+                funds.amount(),
+                self.fee_schedule.net_maker_fee(),
+                time_in_force,
+            );
+            // Insert the limit order into the order book. This panics if the order would be a market order.
+            // The order book will only store a reference to the order (the order key). The order NFR will be given to the
+            // user at the end of this method.
+            self.order_book.insert_limit_order(&order, Runtime::current_epoch());
+
+            // Store the user supplied funds in the correct vault
+            match side {
+                Side::Ask => self.base_funds.put(funds),
+                Side::Bid => self.quote_funds.put(funds),
+            }
+
+            // Mint a new NFR representing the order and give it to the user
+            self.minter.authorize(|| {
+                borrow_resource_manager!(self.order_resource)
+                    .mint_non_fungible(&order_key, order)
+            })
+        }
+
+        /// Aggregates up to the given number of best price levels on the given side of the order book
+        fn get_price_levels(&self, side: Side, levels: u32) -> Vec<PriceLevel> {
+            let resource_manager = borrow_resource_manager!(self.order_resource);
+            self.order_book
+                .get_price_levels(side, Runtime::current_epoch(), levels as usize)
+                .into_iter()
+                .map(|(price, order_keys)| {
+                    let unfilled_quantity = order_keys.iter().fold(Decimal::zero(), |sum, order_key| {
+                        let order: LimitOrder = resource_manager.get_non_fungible_data(order_key);
+                        sum + order.quantity - order.quantity_filled
+                    });
+                    // Bid orders are denominated in the quote resource
+                    let quantity = match side {
+                        Side::Ask => unfilled_quantity,
+                        Side::Bid => unfilled_quantity / price,
+                    };
+                    PriceLevel {
+                        price,
+                        quantity,
+                        order_count: order_keys.len() as u32,
+                    }
+                })
+                .collect()
+        }
+
         /// Infers the side of the order from the resource contained in the given bucket.
         /// If the bucket contains the base resource Ask is inferred.
         /// If the bucket contains the quote resource Bid is inferred.
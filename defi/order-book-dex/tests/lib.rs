@@ -11,7 +11,7 @@ const BLUEPRINT_DEX: &str = "Dex";
 fn test_ask_order_exact_fill() {
     let mut ledger = InMemorySubstateStore::with_bootstrap();
     let env = TestEnv::new(&mut ledger);
-    let mut dex_fixture = setup_fixture(env);
+    let mut dex_fixture = setup_fixture(env, no_fees());
 
     dex_fixture.assert_resource_amount("maker", dex_fixture.base_resource, 1_000_000.into());
     dex_fixture.assert_resource_amount("maker", dex_fixture.quote_resource, 1_000_000.into());
@@ -41,7 +41,7 @@ fn test_ask_order_exact_fill() {
 fn test_bid_order_exact_fill() {
     let mut ledger = InMemorySubstateStore::with_bootstrap();
     let env = TestEnv::new(&mut ledger);
-    let mut dex_fixture = setup_fixture(env);
+    let mut dex_fixture = setup_fixture(env, no_fees());
 
     dex_fixture.assert_resource_amount("maker", dex_fixture.base_resource, 1_000_000.into());
     dex_fixture.assert_resource_amount("maker", dex_fixture.quote_resource, 1_000_000.into());
@@ -71,7 +71,7 @@ fn test_bid_order_exact_fill() {
 fn test_ask_order_partial_fill() {
     let mut ledger = InMemorySubstateStore::with_bootstrap();
     let env = TestEnv::new(&mut ledger);
-    let mut dex_fixture = setup_fixture(env);
+    let mut dex_fixture = setup_fixture(env, no_fees());
 
     dex_fixture.assert_resource_amount("maker", dex_fixture.base_resource, 1_000_000.into());
     dex_fixture.assert_resource_amount("maker", dex_fixture.quote_resource, 1_000_000.into());
@@ -113,7 +113,7 @@ fn test_ask_order_partial_fill() {
 fn test_bid_order_partial_fill() {
     let mut ledger = InMemorySubstateStore::with_bootstrap();
     let env = TestEnv::new(&mut ledger);
-    let mut dex_fixture = setup_fixture(env);
+    let mut dex_fixture = setup_fixture(env, no_fees());
 
     dex_fixture.assert_resource_amount("maker", dex_fixture.base_resource, 1_000_000.into());
     dex_fixture.assert_resource_amount("maker", dex_fixture.quote_resource, 1_000_000.into());
@@ -155,7 +155,7 @@ fn test_bid_order_partial_fill() {
 fn test_ask_order_fractional_numbers() {
     let mut ledger = InMemorySubstateStore::with_bootstrap();
     let env = TestEnv::new(&mut ledger);
-    let mut dex_fixture = setup_fixture(env);
+    let mut dex_fixture = setup_fixture(env, no_fees());
 
     dex_fixture.assert_resource_amount("maker", dex_fixture.base_resource, 1_000_000.into());
     dex_fixture.assert_resource_amount("maker", dex_fixture.quote_resource, 1_000_000.into());
@@ -200,7 +200,7 @@ fn test_ask_order_fractional_numbers() {
 fn test_bid_order_fractional_numbers() {
     let mut ledger = InMemorySubstateStore::with_bootstrap();
     let env = TestEnv::new(&mut ledger);
-    let mut dex_fixture = setup_fixture(env);
+    let mut dex_fixture = setup_fixture(env, no_fees());
 
     dex_fixture.assert_resource_amount("maker", dex_fixture.base_resource, 1_000_000.into());
     dex_fixture.assert_resource_amount("maker", dex_fixture.quote_resource, 1_000_000.into());
@@ -242,10 +242,438 @@ fn test_bid_order_fractional_numbers() {
     dex_fixture.assert_resource_amount("maker", dex_fixture.quote_resource, Decimal::from("999980.0000000000002"),);
 }
 
-fn setup_fixture<L: SubstateStore>(mut env: TestEnv<L>) -> DexFixture<L> {
+#[test]
+fn test_stop_order() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let env = TestEnv::new(&mut ledger);
+    let mut dex_fixture = setup_fixture(env, no_fees());
+
+    dex_fixture.env.acting_as(dex_fixture.user_maker.name);
+    let order1_key = dex_fixture.new_limit_order(
+        FungibleBucket(50_000.into(), dex_fixture.quote_resource),
+        10.into(),
+    );
+    let order2_key = dex_fixture.new_limit_order(
+        FungibleBucket(80_000.into(), dex_fixture.quote_resource),
+        8.into(),
+    );
+    dex_fixture.assert_resource_amount("maker", dex_fixture.quote_resource, 870_000.into());
+
+    // Sell 1_000 XRD once the price drops to 9 or below
+    dex_fixture.env.acting_as(dex_fixture.user_taker.name);
+    let stop_order_key = dex_fixture.new_trigger_order(
+        FungibleBucket(1_000.into(), dex_fixture.base_resource),
+        9.into(),
+        TriggerDirection::Below,
+    );
+    dex_fixture.assert_resource_amount("taker", dex_fixture.base_resource, 999_000.into());
+
+    // A trade at 10 does not cross the trigger price
+    dex_fixture.new_market_order(FungibleBucket(5_000.into(), dex_fixture.base_resource));
+    dex_fixture.assert_resource_amount("taker", dex_fixture.base_resource, 994_000.into());
+    dex_fixture.assert_resource_amount("taker", dex_fixture.quote_resource, 1_050_000.into());
+
+    // A trade at 8 crosses it and the stop order is sold into the bid at 8 as well
+    dex_fixture.new_market_order(FungibleBucket(1_000.into(), dex_fixture.base_resource));
+    dex_fixture.assert_resource_amount("taker", dex_fixture.base_resource, 993_000.into());
+    dex_fixture.assert_resource_amount("taker", dex_fixture.quote_resource, 1_058_000.into());
+
+    dex_fixture.close_limit_order(NonFungibleBucket(stop_order_key, dex_fixture.order_resource));
+    dex_fixture.assert_resource_amount("taker", dex_fixture.base_resource, 993_000.into());
+    dex_fixture.assert_resource_amount("taker", dex_fixture.quote_resource, 1_066_000.into());
+
+    // The bid at 8 has been filled by both the market order and the stop order
+    dex_fixture.env.acting_as(dex_fixture.user_maker.name);
+    dex_fixture.close_limit_order(NonFungibleBucket(order1_key, dex_fixture.order_resource));
+    dex_fixture.close_limit_order(NonFungibleBucket(order2_key, dex_fixture.order_resource));
+    dex_fixture.assert_resource_amount("maker", dex_fixture.base_resource, 1_007_000.into());
+    dex_fixture.assert_resource_amount("maker", dex_fixture.quote_resource, 934_000.into());
+}
+
+#[test]
+fn test_immediate_or_cancel_order() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let env = TestEnv::new(&mut ledger);
+    let mut dex_fixture = setup_fixture(env, no_fees());
+
+    dex_fixture.env.acting_as(dex_fixture.user_maker.name);
+    dex_fixture.new_limit_order(
+        FungibleBucket(1_000.into(), dex_fixture.base_resource),
+        10.into(),
+    );
+
+    // Only 1_000 XRD are on offer, the other 10_000 rUSD are refunded
+    dex_fixture.env.acting_as(dex_fixture.user_taker.name);
+    let order_key = dex_fixture.new_limit_order_with_time_in_force(
+        FungibleBucket(20_000.into(), dex_fixture.quote_resource),
+        10.into(),
+        TimeInForce::ImmediateOrCancel,
+    );
+    assert!(order_key.is_none());
+    dex_fixture.assert_resource_amount("taker", dex_fixture.base_resource, 1_001_000.into());
+    dex_fixture.assert_resource_amount("taker", dex_fixture.quote_resource, 990_000.into());
+
+    // Nothing rests in the order book, so there is no bid to sell into
+    dex_fixture.env.acting_as(dex_fixture.user_maker.name);
+    let receipt = dex_fixture.env.call_method(
+        &dex_fixture.trading_pair_component,
+        "new_market_order",
+        vec![FungibleBucket(100.into(), dex_fixture.base_resource).to_string()],
+    );
+    assert!(receipt.result.is_err());
+}
+
+#[test]
+fn test_fill_or_kill_order() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let env = TestEnv::new(&mut ledger);
+    let mut dex_fixture = setup_fixture(env, no_fees());
+
+    dex_fixture.env.acting_as(dex_fixture.user_maker.name);
+    dex_fixture.new_limit_order(
+        FungibleBucket(1_000.into(), dex_fixture.base_resource),
+        10.into(),
+    );
+
+    // Only 1_000 XRD are on offer, so an order for 2_000 XRD is rejected as a whole
+    dex_fixture.env.acting_as(dex_fixture.user_taker.name);
+    let receipt = dex_fixture.env.call_method(
+        &dex_fixture.trading_pair_component,
+        "new_limit_order_with_time_in_force",
+        vec![
+            FungibleBucket(20_000.into(), dex_fixture.quote_resource).to_string(),
+            "10".to_string(),
+            TimeInForce::FillOrKill.to_string(),
+        ],
+    );
+    assert!(receipt.result.is_err());
+    dex_fixture.assert_resource_amount("taker", dex_fixture.base_resource, 1_000_000.into());
+    dex_fixture.assert_resource_amount("taker", dex_fixture.quote_resource, 1_000_000.into());
+
+    // An order for exactly the 1_000 XRD on offer is filled
+    let order_key = dex_fixture.new_limit_order_with_time_in_force(
+        FungibleBucket(10_000.into(), dex_fixture.quote_resource),
+        10.into(),
+        TimeInForce::FillOrKill,
+    );
+    assert!(order_key.is_none());
+    dex_fixture.assert_resource_amount("taker", dex_fixture.base_resource, 1_001_000.into());
+    dex_fixture.assert_resource_amount("taker", dex_fixture.quote_resource, 990_000.into());
+}
+
+#[test]
+fn test_good_till_epoch_order() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let env = TestEnv::new(&mut ledger);
+    let mut dex_fixture = setup_fixture(env, no_fees());
+
+    // Nothing can be matched, so the whole bid rests in the order book up to and including epoch 5
+    dex_fixture.env.acting_as(dex_fixture.user_taker.name);
+    let order_key = dex_fixture
+        .new_limit_order_with_time_in_force(
+            FungibleBucket(10_000.into(), dex_fixture.quote_resource),
+            10.into(),
+            TimeInForce::GoodTillEpoch(5),
+        )
+        .unwrap();
+    dex_fixture.assert_resource_amount("taker", dex_fixture.quote_resource, 990_000.into());
+
+    dex_fixture.set_current_epoch(5);
+    dex_fixture.env.acting_as(dex_fixture.user_maker.name);
+    dex_fixture.new_market_order(FungibleBucket(100.into(), dex_fixture.base_resource));
+    dex_fixture.assert_resource_amount("maker", dex_fixture.base_resource, 999_900.into());
+    dex_fixture.assert_resource_amount("maker", dex_fixture.quote_resource, 1_001_000.into());
+
+    // After its expiry epoch, the bid is no longer matched
+    dex_fixture.set_current_epoch(6);
+    let receipt = dex_fixture.env.call_method(
+        &dex_fixture.trading_pair_component,
+        "new_market_order",
+        vec![FungibleBucket(100.into(), dex_fixture.base_resource).to_string()],
+    );
+    assert!(receipt.result.is_err());
+    dex_fixture.assert_resource_amount("maker", dex_fixture.base_resource, 999_900.into());
+
+    // But it can still be closed
+    dex_fixture.env.acting_as(dex_fixture.user_taker.name);
+    dex_fixture.close_limit_order(NonFungibleBucket(order_key, dex_fixture.order_resource));
+    dex_fixture.assert_resource_amount("taker", dex_fixture.base_resource, 1_000_100.into());
+    dex_fixture.assert_resource_amount("taker", dex_fixture.quote_resource, 999_000.into());
+}
+
+#[test]
+fn test_claim_filled() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let env = TestEnv::new(&mut ledger);
+    let mut dex_fixture = setup_fixture(env, no_fees());
+
+    dex_fixture.env.acting_as(dex_fixture.user_maker.name);
+    let order_key = dex_fixture.new_limit_order(
+        FungibleBucket(10_000.into(), dex_fixture.base_resource),
+        10.into(),
+    );
+
+    dex_fixture.env.acting_as(dex_fixture.user_taker.name);
+    dex_fixture.new_market_order(FungibleBucket(50_000.into(), dex_fixture.quote_resource));
+
+    // The filled half of the order is paid out and the order stays with the maker
+    dex_fixture.env.acting_as(dex_fixture.user_maker.name);
+    dex_fixture.claim_filled(NonFungibleBucket(order_key.clone(), dex_fixture.order_resource));
+    dex_fixture.assert_resource_amount("maker", dex_fixture.base_resource, 990_000.into());
+    dex_fixture.assert_resource_amount("maker", dex_fixture.quote_resource, 1_050_000.into());
+    assert!(dex_fixture
+        .env
+        .get_non_fungible_keys_for_rd(dex_fixture.user_maker.account, dex_fixture.order_resource)
+        .contains(&order_key));
+
+    // Nothing is paid out twice
+    dex_fixture.claim_filled(NonFungibleBucket(order_key.clone(), dex_fixture.order_resource));
+    dex_fixture.assert_resource_amount("maker", dex_fixture.quote_resource, 1_050_000.into());
+
+    // The order is still in the order book and can be filled completely
+    dex_fixture.env.acting_as(dex_fixture.user_taker.name);
+    dex_fixture.new_market_order(FungibleBucket(50_000.into(), dex_fixture.quote_resource));
+    dex_fixture.assert_resource_amount("taker", dex_fixture.base_resource, 1_010_000.into());
+    dex_fixture.assert_resource_amount("taker", dex_fixture.quote_resource, 900_000.into());
+
+    // Closing the order only pays out what has not been claimed yet
+    dex_fixture.env.acting_as(dex_fixture.user_maker.name);
+    dex_fixture.close_limit_order(NonFungibleBucket(order_key, dex_fixture.order_resource));
+    dex_fixture.assert_resource_amount("maker", dex_fixture.base_resource, 990_000.into());
+    dex_fixture.assert_resource_amount("maker", dex_fixture.quote_resource, 1_100_000.into());
+}
+
+#[test]
+fn test_amend_limit_order() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let env = TestEnv::new(&mut ledger);
+    let mut dex_fixture = setup_fixture(env, no_fees());
+
+    dex_fixture.env.acting_as(dex_fixture.user_maker.name);
+    let order1_key = dex_fixture.new_limit_order(
+        FungibleBucket(10_000.into(), dex_fixture.base_resource),
+        10.into(),
+    );
+    let order2_key = dex_fixture.new_limit_order(
+        FungibleBucket(1_000.into(), dex_fixture.base_resource),
+        9.into(),
+    );
+    dex_fixture.assert_resource_amount("maker", dex_fixture.base_resource, 989_000.into());
+
+    // Move order 1 to a price of 9, behind order 2, and raise its quantity by 5_000 XRD
+    dex_fixture.amend_limit_order(
+        NonFungibleBucket(order1_key.clone(), dex_fixture.order_resource),
+        9.into(),
+        15_000.into(),
+        Some(FungibleBucket(5_000.into(), dex_fixture.base_resource)),
+    );
+    dex_fixture.assert_resource_amount("maker", dex_fixture.base_resource, 984_000.into());
+
+    // Order 2 keeps its priority at a price of 9
+    dex_fixture.env.acting_as(dex_fixture.user_taker.name);
+    dex_fixture.new_market_order(FungibleBucket(9_000.into(), dex_fixture.quote_resource));
+    dex_fixture.assert_resource_amount("taker", dex_fixture.base_resource, 1_001_000.into());
+    dex_fixture.assert_resource_amount("taker", dex_fixture.quote_resource, 991_000.into());
+
+    dex_fixture.env.acting_as(dex_fixture.user_maker.name);
+    dex_fixture.close_limit_order(NonFungibleBucket(order2_key, dex_fixture.order_resource));
+    dex_fixture.assert_resource_amount("maker", dex_fixture.base_resource, 984_000.into());
+    dex_fixture.assert_resource_amount("maker", dex_fixture.quote_resource, 1_009_000.into());
+
+    // Lower the quantity of order 1 to 2_000 XRD, the other 13_000 XRD are refunded
+    dex_fixture.amend_limit_order(
+        NonFungibleBucket(order1_key.clone(), dex_fixture.order_resource),
+        9.into(),
+        2_000.into(),
+        None,
+    );
+    dex_fixture.assert_resource_amount("maker", dex_fixture.base_resource, 997_000.into());
+    dex_fixture.assert_resource_amount("maker", dex_fixture.quote_resource, 1_009_000.into());
+
+    // Order 1 is filled at its new price
+    dex_fixture.env.acting_as(dex_fixture.user_taker.name);
+    dex_fixture.new_market_order(FungibleBucket(18_000.into(), dex_fixture.quote_resource));
+    dex_fixture.assert_resource_amount("taker", dex_fixture.base_resource, 1_003_000.into());
+    dex_fixture.assert_resource_amount("taker", dex_fixture.quote_resource, 973_000.into());
+
+    dex_fixture.env.acting_as(dex_fixture.user_maker.name);
+    dex_fixture.close_limit_order(NonFungibleBucket(order1_key, dex_fixture.order_resource));
+    dex_fixture.assert_resource_amount("maker", dex_fixture.base_resource, 997_000.into());
+    dex_fixture.assert_resource_amount("maker", dex_fixture.quote_resource, 1_027_000.into());
+}
+
+#[test]
+fn test_fees() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let env = TestEnv::new(&mut ledger);
+    let fee_schedule = FeeSchedule {
+        maker_fee: Decimal::from("0.002"),
+        taker_fee: Decimal::from("0.003"),
+        maker_rebate: Decimal::from("0.001"),
+    };
+    let mut dex_fixture = setup_fixture(env, fee_schedule);
+
+    dex_fixture.env.acting_as(dex_fixture.user_maker.name);
+    let order_key = dex_fixture.new_limit_order(
+        FungibleBucket(10_000.into(), dex_fixture.base_resource),
+        10.into(),
+    );
+
+    // The taker pays the exchanged 100_000 plus the taker fee of 0.3%, which fills the order exactly
+    dex_fixture.env.acting_as(dex_fixture.user_taker.name);
+    dex_fixture.new_market_order(FungibleBucket(100_300.into(), dex_fixture.quote_resource));
+    dex_fixture.assert_resource_amount("taker", dex_fixture.base_resource, 1_010_000.into());
+    dex_fixture.assert_resource_amount("taker", dex_fixture.quote_resource, 899_700.into());
+
+    // The maker receives the exchanged 100_000 net of the maker fee of 0.2% and the maker rebate of 0.1%
+    dex_fixture.env.acting_as(dex_fixture.user_maker.name);
+    dex_fixture.close_limit_order(NonFungibleBucket(order_key, dex_fixture.order_resource));
+    dex_fixture.assert_resource_amount("maker", dex_fixture.base_resource, 990_000.into());
+    dex_fixture.assert_resource_amount("maker", dex_fixture.quote_resource, 1_099_900.into());
+
+    // The fee vault of the quote resource has collected the taker fee of 300 and the net maker fee of 100
+    dex_fixture.env.acting_as(dex_fixture.user_admin.name);
+    let admin_base_amount = dex_fixture.env.get_amount_for_rd(dex_fixture.user_admin.account, dex_fixture.base_resource);
+    dex_fixture.withdraw_fees();
+    dex_fixture.assert_resource_amount("admin", dex_fixture.base_resource, admin_base_amount);
+    dex_fixture.assert_resource_amount("admin", dex_fixture.quote_resource, 400.into());
+}
+
+#[test]
+fn test_withdraw_fees_requires_admin_badge() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let env = TestEnv::new(&mut ledger);
+    let fee_schedule = FeeSchedule {
+        maker_fee: Decimal::from("0.002"),
+        taker_fee: Decimal::from("0.003"),
+        maker_rebate: Decimal::zero(),
+    };
+    let mut dex_fixture = setup_fixture(env, fee_schedule);
+
+    dex_fixture.env.acting_as(dex_fixture.user_maker.name);
+    dex_fixture.new_limit_order(
+        FungibleBucket(10_000.into(), dex_fixture.base_resource),
+        10.into(),
+    );
+    dex_fixture.env.acting_as(dex_fixture.user_taker.name);
+    dex_fixture.new_market_order(FungibleBucket(100_300.into(), dex_fixture.quote_resource));
+
+    // Without the admin badge, the fees cannot be withdrawn
+    let receipt = dex_fixture.env.call_method(
+        &dex_fixture.trading_pair_component,
+        "withdraw_fees",
+        vec![],
+    );
+    assert!(receipt.result.is_err());
+    dex_fixture.assert_resource_amount("taker", dex_fixture.quote_resource, 899_700.into());
+
+    // The admin can withdraw them
+    dex_fixture.env.acting_as(dex_fixture.user_admin.name);
+    dex_fixture.withdraw_fees();
+    dex_fixture.assert_resource_amount("admin", dex_fixture.quote_resource, 500.into());
+}
+
+#[test]
+fn test_routed_market_order() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let env = TestEnv::new(&mut ledger);
+    let mut dex_fixture = setup_fixture(env, no_fees());
+    let (eur_pair_component, eur_resource) = setup_eur_trading_pair(&mut dex_fixture);
+
+    // XRD is bought at 10 rUSD on the first pair and rEUR is sold at 3 rUSD on the second pair
+    dex_fixture.env.acting_as(dex_fixture.user_maker.name);
+    dex_fixture.new_limit_order(
+        FungibleBucket(100_000.into(), dex_fixture.quote_resource),
+        10.into(),
+    );
+    let receipt = dex_fixture.env.call_method(
+        &eur_pair_component,
+        "new_limit_order",
+        vec![FungibleBucket(5_000.into(), eur_resource).to_string(), "3".to_string()],
+    );
+    assert!(receipt.result.is_ok());
+
+    // The route goes from XRD to rUSD on the first pair and from rUSD to rEUR on the second pair
+    dex_fixture.env.acting_as(dex_fixture.user_taker.name);
+    dex_fixture.new_routed_market_order(
+        FungibleBucket(1_000.into(), dex_fixture.base_resource),
+        eur_resource,
+        3_333.into(),
+    );
+    dex_fixture.assert_resource_amount("taker", dex_fixture.base_resource, 999_000.into());
+    dex_fixture.assert_resource_amount("taker", eur_resource, Decimal::from("3333.333333333333333333"));
+
+    // The 10_000 rUSD of the first hop cannot be exchanged completely at a price of 3 on the second hop.
+    // The dust of that hop is returned to the taker along with the (empty) dust of the first hop.
+    dex_fixture.assert_resource_amount("taker", dex_fixture.quote_resource, Decimal::from("1000000.000000000000000001"));
+}
+
+#[test]
+fn test_routed_market_order_below_min_output() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let env = TestEnv::new(&mut ledger);
+    let mut dex_fixture = setup_fixture(env, no_fees());
+    let (eur_pair_component, eur_resource) = setup_eur_trading_pair(&mut dex_fixture);
+
+    dex_fixture.env.acting_as(dex_fixture.user_maker.name);
+    dex_fixture.new_limit_order(
+        FungibleBucket(100_000.into(), dex_fixture.quote_resource),
+        10.into(),
+    );
+    let receipt = dex_fixture.env.call_method(
+        &eur_pair_component,
+        "new_limit_order",
+        vec![FungibleBucket(5_000.into(), eur_resource).to_string(), "3".to_string()],
+    );
+    assert!(receipt.result.is_ok());
+
+    // The route yields less than 3_334 rEUR, so the whole transaction is rejected
+    dex_fixture.env.acting_as(dex_fixture.user_taker.name);
+    let receipt = dex_fixture.env.call_method(
+        &dex_fixture.dex_component,
+        "new_routed_market_order",
+        vec![
+            FungibleBucket(1_000.into(), dex_fixture.base_resource).to_string(),
+            eur_resource.to_string(),
+            "3334".to_string(),
+        ],
+    );
+    println!("{:?}", receipt);
+    assert!(receipt.result.is_err());
+    dex_fixture.assert_resource_amount("taker", dex_fixture.base_resource, 1_000_000.into());
+    dex_fixture.assert_resource_amount("taker", dex_fixture.quote_resource, 1_000_000.into());
+    dex_fixture.assert_resource_amount("taker", eur_resource, 0.into());
+}
+
+/// Creates 1_000_000 rEUR owned by the maker and adds a rEUR/rUSD trading pair to the Dex.
+/// Returns the address of the TradingPair component and the address of rEUR.
+fn setup_eur_trading_pair<L: SubstateStore>(dex_fixture: &mut DexFixture<L>) -> (Address, Address) {
+    dex_fixture.env.acting_as(dex_fixture.user_maker.name);
+    let eur_resource = dex_fixture.env.create_token(1_000_000.into()).address();
+
+    dex_fixture.env.acting_as(dex_fixture.user_admin.name);
+    let receipt = dex_fixture.env.call_method(
+        &dex_fixture.dex_component,
+        "add_trading_pair",
+        vec![
+            format!("{}", eur_resource),
+            format!("{}", dex_fixture.quote_resource),
+            "0".to_string(),
+            "0".to_string(),
+            "0".to_string(),
+            format!("1,{}", dex_fixture.admin_badge),
+        ],
+    );
+    assert!(receipt.result.is_ok());
+
+    (receipt.component(0).unwrap(), eur_resource)
+}
+
+fn setup_fixture<L: SubstateStore>(mut env: TestEnv<L>, fee_schedule: FeeSchedule) -> DexFixture<L> {
+    let admin_name = "admin";
     let maker_name = "maker";
     let taker_name = "taker";
-    let _admin = env.create_user("admin");
+    let admin = env.create_user(admin_name);
     let maker = env.create_user(maker_name);
     let taker = env.create_user(taker_name);
 
@@ -272,6 +700,9 @@ fn setup_fixture<L: SubstateStore>(mut env: TestEnv<L>) -> DexFixture<L> {
         vec![
             format!("{}", base_resource),
             format!("{}", quote_resource),
+            fee_schedule.maker_fee.to_string(),
+            fee_schedule.taker_fee.to_string(),
+            fee_schedule.maker_rebate.to_string(),
             format!("1,{}", admin_badge),
         ],
     );
@@ -281,11 +712,16 @@ fn setup_fixture<L: SubstateStore>(mut env: TestEnv<L>) -> DexFixture<L> {
 
     DexFixture {
         env,
-        _dex_component: dex_component,
+        dex_component: dex_component,
         trading_pair_component,
         base_resource,
         quote_resource,
         order_resource,
+        admin_badge,
+        user_admin: DexUser {
+            account: admin.account,
+            name: admin_name,
+        },
         user_maker: DexUser {
             account: maker.account,
             name: maker_name,
@@ -304,17 +740,64 @@ struct DexUser {
 
 struct DexFixture<'a, L: SubstateStore> {
     env: TestEnv<'a, L>,
-    _dex_component: Address,
+    dex_component: Address,
     trading_pair_component: Address,
     base_resource: Address,
     quote_resource: Address,
     order_resource: Address,
+    admin_badge: Address,
+    user_admin: DexUser,
     user_maker: DexUser,
     user_taker: DexUser,
 }
 
 impl<'a, L: SubstateStore> DexFixture<'a, L> {
     fn new_limit_order(&mut self, funds: FungibleBucket, price: Decimal) -> NonFungibleKey {
+        self.new_order("new_limit_order", vec![funds.to_string(), price.to_string()])
+    }
+
+    fn new_trigger_order(
+        &mut self,
+        funds: FungibleBucket,
+        trigger_price: Decimal,
+        direction: TriggerDirection,
+    ) -> NonFungibleKey {
+        self.new_order(
+            "new_trigger_order",
+            vec![funds.to_string(), trigger_price.to_string(), direction.to_string()],
+        )
+    }
+
+    /// Creates a limit order with the given time in force.
+    /// Returns the key of the order NFR if some of the order rests in the order book, else None
+    fn new_limit_order_with_time_in_force(
+        &mut self,
+        funds: FungibleBucket,
+        price: Decimal,
+        time_in_force: TimeInForce,
+    ) -> Option<NonFungibleKey> {
+        let order_keys_before: HashSet<NonFungibleKey> = self
+            .env
+            .get_non_fungible_keys_for_rd(self.env.current_user.unwrap().account, self.order_resource)
+            .into_iter()
+            .collect();
+
+        let receipt = self.env.call_method(
+            &self.trading_pair_component,
+            "new_limit_order_with_time_in_force",
+            vec![funds.to_string(), price.to_string(), time_in_force.to_string()],
+        );
+        println!("{:?}", receipt);
+        assert!(receipt.result.is_ok());
+
+        self.env
+            .get_non_fungible_keys_for_rd(self.env.current_user.unwrap().account, self.order_resource)
+            .into_iter()
+            .find(|order_key| !order_keys_before.contains(order_key))
+    }
+
+    /// Calls the given method to create an order and returns the key of the order NFR that the current user has received
+    fn new_order(&mut self, method: &str, args: Vec<String>) -> NonFungibleKey {
         let order_keys_before = self.env.get_non_fungible_keys_for_rd(
             self.env.current_user.unwrap().account,
             self.order_resource,
@@ -322,11 +805,8 @@ impl<'a, L: SubstateStore> DexFixture<'a, L> {
         let order_keys_before: HashSet<NonFungibleKey> =
             order_keys_before.iter().cloned().collect();
 
-        let receipt = self.env.call_method(
-            &self.trading_pair_component,
-            "new_limit_order",
-            vec![funds.to_string(), price.to_string()],
-        );
+        let receipt = self.env.call_method(&self.trading_pair_component, method, args);
+        println!("{:?}", receipt);
         assert!(receipt.result.is_ok());
 
         let order_keys_now = self.env.get_non_fungible_keys_for_rd(
@@ -354,6 +834,36 @@ impl<'a, L: SubstateStore> DexFixture<'a, L> {
         assert!(receipt.result.is_ok());
     }
 
+    fn claim_filled(&mut self, order: NonFungibleBucket) {
+        let receipt = self.env.call_method(
+            &self.trading_pair_component,
+            "claim_filled",
+            vec![order.to_string()],
+        );
+        println!("{:?}", receipt);
+        assert!(receipt.result.is_ok());
+    }
+
+    fn amend_limit_order(
+        &mut self,
+        order: NonFungibleBucket,
+        price: Decimal,
+        quantity: Decimal,
+        funds: Option<FungibleBucket>,
+    ) {
+        let funds = match funds {
+            Some(funds) => format!("Some({})", funds.to_string()),
+            None => "None".to_string(),
+        };
+        let receipt = self.env.call_method(
+            &self.trading_pair_component,
+            "amend_limit_order",
+            vec![order.to_string(), price.to_string(), quantity.to_string(), funds],
+        );
+        println!("{:?}", receipt);
+        assert!(receipt.result.is_ok());
+    }
+
     fn new_market_order(&mut self, funds: FungibleBucket) {
         let receipt = self.env.call_method(
             &self.trading_pair_component,
@@ -364,8 +874,34 @@ impl<'a, L: SubstateStore> DexFixture<'a, L> {
         assert!(receipt.result.is_ok());
     }
 
+    fn new_routed_market_order(&mut self, funds: FungibleBucket, target_resource: Address, min_output: Decimal) {
+        let receipt = self.env.call_method(
+            &self.dex_component,
+            "new_routed_market_order",
+            vec![funds.to_string(), target_resource.to_string(), min_output.to_string()],
+        );
+        println!("{:?}", receipt);
+        assert!(receipt.result.is_ok());
+    }
+
+    fn withdraw_fees(&mut self) {
+        let receipt = self.env.call_method(
+            &self.trading_pair_component,
+            "withdraw_fees",
+            vec![format!("1,{}", self.admin_badge)],
+        );
+        println!("{:?}", receipt);
+        assert!(receipt.result.is_ok());
+    }
+
+    fn set_current_epoch(&mut self, epoch: u64) {
+        self.env.executor.ledger_mut().set_epoch(epoch);
+    }
+
     fn assert_resource_amount(&mut self, user: &str, resource: Address, expected_amount: Decimal) {
-        let account = if user == self.user_maker.name {
+        let account = if user == self.user_admin.name {
+            self.user_admin.account
+        } else if user == self.user_maker.name {
             self.user_maker.account
         } else if user == self.user_taker.name {
             self.user_taker.account
@@ -382,6 +918,51 @@ impl<'a, L: SubstateStore> DexFixture<'a, L> {
     }
 }
 
+/// The fees passed to add_trading_pair
+struct FeeSchedule {
+    maker_fee: Decimal,
+    taker_fee: Decimal,
+    maker_rebate: Decimal,
+}
+
+fn no_fees() -> FeeSchedule {
+    FeeSchedule {
+        maker_fee: Decimal::zero(),
+        taker_fee: Decimal::zero(),
+        maker_rebate: Decimal::zero(),
+    }
+}
+
+enum TimeInForce {
+    ImmediateOrCancel,
+    FillOrKill,
+    GoodTillEpoch(u64),
+}
+
+impl TimeInForce {
+    fn to_string(&self) -> String {
+        match self {
+            TimeInForce::ImmediateOrCancel => "ImmediateOrCancel".to_string(),
+            TimeInForce::FillOrKill => "FillOrKill".to_string(),
+            TimeInForce::GoodTillEpoch(epoch) => format!("GoodTillEpoch({})", epoch),
+        }
+    }
+}
+
+enum TriggerDirection {
+    Above,
+    Below,
+}
+
+impl TriggerDirection {
+    fn to_string(&self) -> String {
+        match self {
+            TriggerDirection::Above => "Above".to_string(),
+            TriggerDirection::Below => "Below".to_string(),
+        }
+    }
+}
+
 struct FungibleBucket(Decimal, Address);
 
 impl FungibleBucket {
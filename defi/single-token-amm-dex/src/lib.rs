@@ -27,7 +27,9 @@ blueprint! {
         // Amount of accrued XRD protocol fee withdrawed by protocol owner.                                      
         xrd_claimed: Decimal,                                     
         // Protocol fee variable.
-        fee: Decimal                                              
+        fee: Decimal,
+        // Flashswap receipt resource, not depositable, to be burned repaying a flashswap.
+        flashswap_receipt: ResourceAddress
     }
 
     impl CandyDex {
@@ -42,6 +44,13 @@ blueprint! {
                 .metadata("name", " OwnerBadge ")
                 .initial_supply(1);
 
+            let flashswap_receipt = ResourceBuilder::new_non_fungible()
+                .metadata("name", " FlashswapReceipt ")
+                .mintable(rule!(require(minter_badge.resource_address())), LOCKED)
+                .burnable(rule!(require(minter_badge.resource_address())), LOCKED)
+                .restrict_deposit(rule!(deny_all), LOCKED)
+                .no_initial_supply();
+
             let component = Self {
                 collected_xrd: Vault::new(RADIX_TOKEN),
                 candy_vaults: HashMap::new(),
@@ -53,7 +62,8 @@ blueprint! {
                 owner_badge: badge_bucket.resource_address(),
                 xrd_fee: Decimal::zero(),
                 xrd_claimed: Decimal::zero(),
-                fee
+                fee,
+                flashswap_receipt
             }
             .instantiate();
 
@@ -492,25 +502,49 @@ blueprint! {
         }
 
             // Retrieve liquidity provider position providing a relative metaBadge as reference.
-        pub fn stock_position(&mut self, meta_badge: Proof) {
+        pub fn stock_position(&mut self, meta_badge: Proof) -> StockPosition {
             let badge_amnt: Decimal = meta_badge.amount();
             assert!( badge_amnt >= Decimal::one(), " Please provide your own metaBadge as reference ");
-            
-            match self.badge_map.get(&meta_badge.resource_address()) {
-                Some((a,b,c)) => { 
-                    let (accrued_fee,total_minted,_meta_address) = self.meta_map.get(&c).unwrap();
-                    let delta_fee: Decimal = *accrued_fee-(*a);
-                    let candy_out_nbr = *b+delta_fee*(*b)/(*total_minted);
-                    info!(" accrued_fee {} ",accrued_fee);
-                    info!(" total_minted {} ",total_minted);
-                    info!(" entry_fee {} ",a);
-                    info!(" delta_fee {} ",delta_fee);
-                    info!(" candy_out_nbr {} ",candy_out_nbr);
-                },
-                None => {
-                    info!(" No badge's correspondence! ");
-                    std::process::abort()
-                }
+
+            let position = CandyDex::get_position(self, meta_badge.resource_address());
+            info!(" Position {:?} ", position);
+
+            position
+        }
+
+            // Get liquidity provider position relative to a metaBadge address: current candy and XRD 
+            // claim, accrued fee since entry and impermanent loss against holding stocked candy.
+        pub fn get_position(&self, meta_badge_addr: ResourceAddress) -> StockPosition {
+            let (entry_fee,meta_amnt,candy_addr) = match self.badge_map.get(&meta_badge_addr) {
+                Some(entry) => *entry,
+                None => panic!(" No badge's correspondence! ")
+            };
+            let (accrued_fee,total_minted,_meta_address) = *self.meta_map.get(&candy_addr).unwrap();
+            let (_name,_symbol,price) = self.candy_map.get(&candy_addr).unwrap();
+            let price = *price;
+
+            // Candy amount claimable by unstocking, same as "unstock_candy" computation.
+            let delta_fee: Decimal = accrued_fee-entry_fee;
+            let fee_amnt = delta_fee*meta_amnt/total_minted;
+            let candy_out_nbr = meta_amnt+fee_amnt;
+
+            // Candy are claimed first, remainder is reimbursed in XRD at current candy price.
+            let total_candy = self.candy_vaults.get(&candy_addr).unwrap().amount();
+            let candy_claim = std::cmp::min(candy_out_nbr, total_candy);
+            let xrd_claim = std::cmp::min((candy_out_nbr-candy_claim)*price, self.collected_xrd.amount());
+
+            let position_value = candy_claim*price+xrd_claim;
+            let hold_value = meta_amnt*price;
+
+            StockPosition {
+                candy_address: candy_addr,
+                meta_amount: meta_amnt,
+                candy_claim,
+                xrd_claim,
+                accrued_fee: fee_amnt,
+                position_value,
+                hold_value,
+                impermanent_loss: hold_value-(position_value-fee_amnt*price)
             }
         }
 
@@ -765,78 +799,134 @@ blueprint! {
             )
         }
 
-            // Request a flashswap performing a call to an external Component address.
+            // Request a flashswap lending candies or XRD. Loan has to be repaid in the same transaction with
+            // the requested candy or XRD, protocol fee in addiction, calling "repay_flashswap" with the returned
+            // receipt, which can't be deposited and is burned only when loan is repaid.
         pub fn flashswap(
             &mut self, 
             amnt_in: Decimal, 
             addr_in: ResourceAddress, 
-            bckt_addr: ResourceAddress, 
-            ext_addr: ComponentAddress, 
-            method: String
-        ) -> Bucket {                
-            let amount_in = CandyDex::adjust_fee(self, amnt_in);
-            
-            let token_bucket: Bucket;
-            let token_output: Bucket;
+            repay_addr: ResourceAddress
+        ) -> (Bucket,Bucket) {
+            assert!(amnt_in > Decimal::zero(), " Flashswap amount must be positive! ");
+
+            let price_in = CandyDex::flashswap_price(self, addr_in);
+            let price_out = CandyDex::flashswap_price(self, repay_addr);
+
+            let loan = if addr_in == RADIX_TOKEN {
+                assert!(amnt_in <= self.collected_xrd.amount(), " Not enough XRD in Vault ");
+                self.collected_xrd.take(amnt_in)
+            } else {
+                let v = self.candy_vaults.get_mut(&addr_in).unwrap();
+                assert!(amnt_in <= v.amount(), " Not enough candies in Vault ");
+                v.take(amnt_in)
+            };
 
-            let price_in: Decimal;
-            let price_out: Decimal;
-            
-            if  addr_in == bckt_addr {    
-                price_in = Decimal::one();
-                price_out = Decimal::one();
+            let fee = amnt_in*self.fee/100*price_in/price_out;
+            let amount_due = amnt_in*price_in/price_out+fee;
+
+            let receipt = self.minter_badge.authorize(|| {
+                borrow_resource_manager!(self.flashswap_receipt).mint_non_fungible(
+                    &NonFungibleId::random(),
+                    FlashSwapDue {
+                        loan_address: addr_in,
+                        loan_amount: amnt_in,
+                        repay_address: repay_addr,
+                        amount_due,
+                        fee
+                    }
+                )
+            });
 
-                if addr_in == RADIX_TOKEN {
-                    token_bucket = self.collected_xrd.take(*&(amount_in-amount_in*self.fee/100));
-                    self.xrd_fee = self.xrd_fee+amount_in*self.fee/100;
-                } else { 
-                    token_bucket = CandyDex::candytake(self, amount_in, addr_in); 
-                }
+            (loan,receipt)
+        }
 
-            } else if addr_in == RADIX_TOKEN && addr_in != bckt_addr {
-                token_bucket = self.collected_xrd.take(*&(amount_in-amount_in*self.fee/100));
-                self.xrd_fee = self.xrd_fee+amount_in*self.fee/100;
-                price_in = Decimal::one();
-                price_out = CandyDex::candyprice(self, bckt_addr);
-            } else if addr_in != bckt_addr && bckt_addr == RADIX_TOKEN {
-                token_bucket = CandyDex::candytake(self, amount_in, addr_in);
-                price_in = CandyDex::candyprice(self, addr_in);
-                price_out = Decimal::one();
-            } else if bckt_addr != RADIX_TOKEN {
-                token_bucket = CandyDex::candytake(self, amount_in, addr_in);
-                price_in = CandyDex::candyprice(self, addr_in);
-                price_out = CandyDex::candyprice(self, bckt_addr);
-            } else { 
-                info!(" Check out addresses! "); 
-                std::process::abort(); 
+            // Repay a flashswap with the candy or XRD requested, burning the flashswap receipt. 
+            // Exceeding amount is returned.
+        pub fn repay_flashswap(&mut self, mut repayment: Bucket, receipt: Bucket) -> Bucket {
+            assert!(
+                receipt.resource_address() == self.flashswap_receipt, 
+                " Wrong flashswap receipt provided! "
+            );
+            let due: FlashSwapDue = receipt.non_fungible().data();
+
+            assert!(
+                repayment.resource_address() == due.repay_address, 
+                " Flashswap must be repaid with {} resource! ", due.repay_address
+            );
+            assert!(
+                repayment.amount() >= due.amount_due, 
+                " Insufficient flashswap repayment: {} provided, {} due of which {} protocol fee ", 
+                repayment.amount(), due.amount_due, due.fee
+            );
+
+            if due.repay_address == RADIX_TOKEN {
+                self.xrd_fee = self.xrd_fee+due.fee;
+                self.collected_xrd.put(repayment.take(due.amount_due));
+            } else {
+                match self.meta_map.get_mut(&due.repay_address) {
+                    Some((amnt_fee,_b,_c)) => *amnt_fee = *amnt_fee+due.fee,
+                    None => panic!(" Candy not in stock! ")
+                };
+                self.candy_vaults.get_mut(&due.repay_address).unwrap().put(repayment.take(due.amount_due));
             }
-            
-            let args = vec![scrypto_encode(&token_bucket),scrypto_encode(&bckt_addr)];
-            
-            let token_return = borrow_component!(ext_addr).call::<Bucket>(&method.to_string(), args);
 
-            let amount = token_return.amount();
-            let nmbr = (amnt_in+amnt_in*self.fee/100)*price_in/price_out;
-            
-            if bckt_addr != RADIX_TOKEN {   
-                let v = self.candy_vaults.get_mut(&bckt_addr).unwrap();
+            self.minter_badge.authorize(|| receipt.burn());
 
-                v.put(token_return);
+            repayment
+        }
 
-                token_output = v.take(amount-nmbr);    
-            } else { 
-                self.collected_xrd.put(token_return);
-                token_output = self.collected_xrd.take(*&(amount-nmbr)); 
+            // Retrieve price of a flashswap resource, either XRD or a listed candy.
+            fn flashswap_price(&self, addr: ResourceAddress) -> Decimal {
+                if addr == RADIX_TOKEN {
+                    Decimal::one()
+                } else {
+                    match self.candy_map.get(&addr) {
+                        Some((_a,_b,price)) => *price,
+                        None => panic!(" Candy not in stock! ")
+                    }
+                }
             }
+    }
+}
 
-            if token_output.amount() < dec!(0) {
-               info!(" Sorry mate, ain't nothin' to scrape! ");
-               std::process::abort();
-            }
+// Liquidity provider position relative to a metaBadge, as returned by "stock_position" and "get_position".
+// Values are in XRD at current candy price.
+#[derive(Debug, Clone, TypeId, Encode, Decode, Describe, PartialEq, Eq)]
+pub struct StockPosition {
+    // Stocked candy address.
+    pub candy_address: ResourceAddress,
+    // metaCandy amount relative to metaBadge, equal to stocked candy amount.
+    pub meta_amount: Decimal,
+    // Candy amount claimable unstocking metaCandy, accrued fee included.
+    pub candy_claim: Decimal,
+    // XRD amount claimable as difference when there isn't enough candy in protocol's vault.
+    pub xrd_claim: Decimal,
+    // Candy fee accrued since entry position.
+    pub accrued_fee: Decimal,
+    // Value of candy and XRD claim.
+    pub position_value: Decimal,
+    // Value of stocked candy amount if held instead.
+    pub hold_value: Decimal,
+    // Value lost against holding stocked candy, accrued fee excluded. Zero unless protocol's vaults 
+    // can't fully reimburse position.
+    pub impermanent_loss: Decimal,
+}
 
-            token_output
-        }
-    }
+// Flashswap terms, minted as a receipt by "flashswap" and burned by "repay_flashswap".
+// Amount due and fee are in repay resource.
+#[derive(NonFungibleData)]
+pub struct FlashSwapDue {
+    // Lent candy or XRD address.
+    pub loan_address: ResourceAddress,
+    // Lent amount.
+    pub loan_amount: Decimal,
+    // Candy or XRD address to repay flashswap with.
+    pub repay_address: ResourceAddress,
+    // Amount to repay, protocol fee included.
+    pub amount_due: Decimal,
+    // Protocol fee.
+    pub fee: Decimal,
 }
 
 // Build a structure and implement it to populate a meta hashmap and relate metaCandy resource with 
@@ -23,6 +23,15 @@ async function e(command, isQuiet = true) {
   return outputs
 }
 
+async function expectFailure(command, reason) {
+  try {
+    await quiet(command)
+  } catch (p) {
+    return
+  }
+  throw new Error(`Transaction should have failed: ${reason}`)
+}
+
 resetEnvs()
 await $`resim reset`
 
@@ -55,7 +64,7 @@ setEnvValue('privkey2', privkey2);
 const pub_package = (await e($`resim publish .`))[0]
 setEnvValue('package', pub_package);
 
-output = await e($`resim call-function  ${pub_package} LiquidityPool new 1000,${tokenXRD} "LPT" "LP_Token"`)
+output = await e($`resim call-function  ${pub_package} LiquidityPool new 1000,${tokenXRD} "LPT" "LP_Token" 0`)
 const component = output[0]
 const lp_mint_bage = output[1]
 const lp_token = output[2]
@@ -102,4 +111,32 @@ await e($`resim show ${account1}`, false)
 await e($`resim show ${account2}`, false)
 await e($`resim show ${account}`, false)
 
+// Pool with a withdrawal cooldown of 10 epochs
 
+await e($`resim set-current-epoch 0`)
+await e($`resim set-default-account ${account} ${privkey}`)
+output = await e($`resim call-function  ${pub_package} LiquidityPool new 1000,${tokenXRD} "LPC" "LP_Cooldown" 10`)
+const cooldown_component = output[0]
+const cooldown_lp_token = output[2]
+const withdrawal_ticket = output[3]
+setEnvValue('cooldown_component', cooldown_component);
+setEnvValue('cooldown_lp_token', cooldown_lp_token);
+setEnvValue('withdrawal_ticket', withdrawal_ticket);
+
+await e($`resim set-default-account ${account1} ${privkey1}`)
+await e($`resim call-method ${cooldown_component} add_liquidity 100,${tokenXRD}`)
+
+// Liquidity can't be removed at once
+await expectFailure($`resim call-method ${cooldown_component} remove_liquidity 100,${cooldown_lp_token}`, "remove_liquidity with a cooldown")
+
+// Request the withdrawal, it can't be completed before the cooldown is over
+await e($`resim call-method ${cooldown_component} request_withdrawal 100,${cooldown_lp_token}`)
+await e($`resim set-current-epoch 5`)
+await expectFailure($`resim call-method ${cooldown_component} complete_withdrawal 1,${withdrawal_ticket}`, "complete_withdrawal before the cooldown")
+
+await e($`resim set-current-epoch 10`)
+await e($`resim call-method ${cooldown_component} complete_withdrawal 1,${withdrawal_ticket}`)
+
+// account1 got its 100 XRD back and holds no ticket anymore
+await e($`resim show ${account1}`, false)
+await e($`resim show ${cooldown_component}`, false)
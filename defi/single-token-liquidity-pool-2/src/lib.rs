@@ -1,20 +1,35 @@
 use scrypto::prelude::*;
 
+/// A queued withdrawal, which can be completed from claimable_epoch on
+#[derive(NonFungibleData)]
+pub struct WithdrawalTicket {
+    amount: Decimal,
+    claimable_epoch: u64,
+}
+
 blueprint! {
     struct LiquidityPool {
         pool: Vault,
         lp_mint_badge: Vault,
         lp_resource_address: ResourceAddress,
         lp_per_asset_ratio:Decimal,
+        // Number of epochs between requesting and completing a withdrawal, 0 to withdraw at once
+        cooldown_epochs: u64,
+        withdrawal_ticket_address: ResourceAddress,
+        ticket_counter: u64,
+        // Amount owed to queued withdrawals. It stays in the pool but is no longer liquidity.
+        queued_amount: Decimal,
     }
 
     impl LiquidityPool {
         /// Creates a LiquidityPool component and returns the component address
         /// along with the initial LP tokens.
+        /// With a cooldown, liquidity can only be removed by requesting a withdrawal and completing it cooldown_epochs later.
         pub fn new(
             tokens: Bucket,
             lp_symbol: String,
             lp_name: String,
+            cooldown_epochs: u64,
         ) -> (ComponentAddress,Bucket) {
 
             // Check arguments
@@ -43,6 +58,13 @@ blueprint! {
                 .burnable(rule!(require(lp_mint_badge.resource_address())), LOCKED)
                 .initial_supply(tokens.amount());
             let lp_resource_address = lp_tokens.resource_address();
+
+            // Define the tickets of queued withdrawals
+            let withdrawal_ticket_address = ResourceBuilder::new_non_fungible()
+                .metadata("name", "Withdrawal ticket")
+                .mintable(rule!(require(lp_mint_badge.resource_address())), LOCKED)
+                .burnable(rule!(require(lp_mint_badge.resource_address())), LOCKED)
+                .no_initial_supply();
             
             let liquidity_pool = Self {
                 pool: Vault::with_bucket(tokens),
@@ -51,6 +73,10 @@ blueprint! {
                 lp_resource_address,
 
                 lp_per_asset_ratio: Decimal::one(),
+                cooldown_epochs,
+                withdrawal_ticket_address,
+                ticket_counter: 0,
+                queued_amount: Decimal::zero(),
             }
             .instantiate()
             .globalize();
@@ -91,7 +117,7 @@ blueprint! {
 
             let lp_resource_manager = borrow_resource_manager!(self.lp_resource_address);
             if lp_resource_manager.total_supply() !=  Decimal::zero() {
-                self.lp_per_asset_ratio = lp_resource_manager.total_supply() / self.pool.amount();
+                self.lp_per_asset_ratio = lp_resource_manager.total_supply() / self.liquidity();
             } else {
                 self.lp_per_asset_ratio = Decimal::one()
             }
@@ -101,6 +127,10 @@ blueprint! {
         pub fn remove_liquidity(&mut self,
              lp_tokens: Bucket
         ) -> Bucket {
+            assert!(
+                self.cooldown_epochs == 0,
+                "Withdrawals have a cooldown of {} epochs, use request_withdrawal", self.cooldown_epochs
+            );
             assert!(
                 self.lp_resource_address == lp_tokens.resource_address(),
                 "Wrong token type passed in"
@@ -110,7 +140,7 @@ blueprint! {
             let  to_remove = lp_tokens.amount()/(self.lp_per_asset_ratio);
 
             // Remain residual liquidity will be withdrawl on the last withdrawal  
-            let withdrawn = self.pool.take(std::cmp::min(to_remove, self.pool.amount()));
+            let withdrawn = self.pool.take(std::cmp::min(to_remove, self.liquidity()));
            
             // Burn the LP tokens received
             self.lp_mint_badge.authorize(|| {
@@ -118,12 +148,84 @@ blueprint! {
             });
 
             // Rest lp_per_asset_ratio if the pool is empty
-            if self.pool.amount() ==  Decimal::zero() {
+            if self.liquidity() ==  Decimal::zero() {
                 self.lp_per_asset_ratio = Decimal::one()
             }
 
             // Return the withdrawn tokens
             withdrawn
         }
+
+        /// Requests the withdrawal of the liquidity share of the LP tokens, which are burned.
+        /// The amount is fixed now and no longer earns fees. Returns a ticket to complete the withdrawal after the cooldown.
+        pub fn request_withdrawal(&mut self,
+             lp_tokens: Bucket
+        ) -> Bucket {
+            assert!(
+                self.lp_resource_address == lp_tokens.resource_address(),
+                "Wrong token type passed in"
+            );
+
+            let amount = std::cmp::min(lp_tokens.amount()/(self.lp_per_asset_ratio), self.liquidity());
+
+            // Burn the LP tokens received
+            self.lp_mint_badge.authorize(|| {
+                lp_tokens.burn();
+            });
+
+            // Queue the withdrawal
+            self.ticket_counter += 1;
+            self.queued_amount = self.queued_amount + amount;
+
+            // Rest lp_per_asset_ratio if the pool is empty
+            if self.liquidity() ==  Decimal::zero() {
+                self.lp_per_asset_ratio = Decimal::one()
+            }
+
+            let ticket = WithdrawalTicket {
+                amount,
+                claimable_epoch: Runtime::current_epoch() + self.cooldown_epochs,
+            };
+            self.lp_mint_badge.authorize(|| {
+                borrow_resource_manager!(self.withdrawal_ticket_address)
+                    .mint_non_fungible(&NonFungibleId::from_u64(self.ticket_counter), ticket)
+            })
+        }
+
+        /// Completes a queued withdrawal once its cooldown is over, burning the ticket.
+        /// The queued amount is kept out of the liquidity, so the pool always holds enough tokens to pay it.
+        pub fn complete_withdrawal(&mut self,
+             ticket: Bucket
+        ) -> Bucket {
+            assert!(
+                self.withdrawal_ticket_address == ticket.resource_address(),
+                "Wrong token type passed in"
+            );
+
+            let data = ticket.non_fungible::<WithdrawalTicket>().data();
+            assert!(
+                Runtime::current_epoch() >= data.claimable_epoch,
+                "The withdrawal can only be completed from epoch {}", data.claimable_epoch
+            );
+
+            self.queued_amount = self.queued_amount - data.amount;
+
+            // Burn the ticket
+            self.lp_mint_badge.authorize(|| {
+                ticket.burn();
+            });
+
+            self.pool.take(data.amount)
+        }
+
+        /// Amount owed to queued withdrawals
+        pub fn queued_amount(&self) -> Decimal {
+            self.queued_amount
+        }
+
+        // Tokens of the pool owned by liquidity providers, without the ones owed to queued withdrawals
+        fn liquidity(&self) -> Decimal {
+            self.pool.amount() - self.queued_amount
+        }
     }
 }
@@ -2,19 +2,32 @@ use scrypto::prelude::*;
 
 /*
  * Liquidity pool with a single token and fees collection mechanism.
- * This component is not meant to be used by itself. 
+ * This component is not meant to be used by itself.
  * You should use it inside another component.
+ *
+ * Fees can be streamed: instead of joining the pool at once, they are released into it
+ * linearly over a number of epochs, so that a large fee deposit can't be front-run
+ * by adding liquidity just before it and removing it just after.
  */
 blueprint! {
     struct SingleTokenLiquidityPool {
         pool: Vault,
+        // Fees not released into the pool yet
         fees: Vault,
         lp_minter_badge: Vault,
-        lp_token: ResourceAddress
+        lp_token: ResourceAddress,
+        // Number of epochs over which fees are released, 0 to release them at once
+        fee_stream_epochs: u64,
+        // Fees released per epoch until the end of the stream
+        fee_release_rate: Decimal,
+        fee_stream_end: u64,
+        last_release: u64,
+        // Fees earned by a single LP token at every epoch in which fees were released
+        earnings_history: Vec<(u64, Decimal)>
     }
 
     impl SingleTokenLiquidityPool {
-        pub fn new(initial_funds: Bucket, lp_initial_supply: Decimal) -> ComponentAddress {
+        pub fn new(initial_funds: Bucket, lp_initial_supply: Decimal, fee_stream_epochs: u64) -> ComponentAddress {
             let funds_resource_def = initial_funds.resource_address();
 
             // Create badge that will be used to mint and burn LP tokens
@@ -39,19 +52,35 @@ blueprint! {
                 pool: Vault::with_bucket(initial_funds),
                 fees: Vault::new(funds_resource_def),
                 lp_minter_badge: Vault::with_bucket(lp_minter),
-                lp_token: lp_token
+                lp_token: lp_token,
+                fee_stream_epochs: fee_stream_epochs,
+                fee_release_rate: Decimal::zero(),
+                fee_stream_end: Runtime::current_epoch(),
+                last_release: Runtime::current_epoch(),
+                earnings_history: Vec::new()
 
             }
             .instantiate().globalize()
         }
 
-        // Will be called by other components
+        // Will be called by other components.
+        // The fees not released yet are added to the new ones, and all of them are streamed over the next fee_stream_epochs
         pub fn add_fees(&mut self, fees: Bucket) {
+            self.release_fees();
             self.fees.put(fees);
+
+            if self.fee_stream_epochs == 0 {
+                self.release_fees();
+            } else {
+                self.fee_release_rate = self.fees.amount() / self.fee_stream_epochs;
+                self.fee_stream_end = Runtime::current_epoch() + self.fee_stream_epochs;
+            }
         }
 
         // Contribute tokens to the pool in exchange of an LP token
         pub fn add_liquidity(&mut self, liquidity: Bucket) -> Bucket {
+            self.release_fees();
+
             let pool_share = liquidity.amount() / self.pool.amount();
             self.pool.put(liquidity);
 
@@ -62,21 +91,63 @@ blueprint! {
             })
         }
 
-        // Give LP token back to get portion of the pool and fees
+        // Give LP token back to get portion of the pool, released fees included
         pub fn remove_liquidity(&mut self, lp_tokens: Bucket) -> Bucket {
             assert!(lp_tokens.resource_address() == self.lp_token, "Wrong LP token !");
+            self.release_fees();
 
             let share = lp_tokens.amount() / borrow_resource_manager!(self.lp_token).total_supply();
-            
+
             // Burn the provided LP token
             self.lp_minter_badge.authorize(|| {
                 lp_tokens.burn();
             });
 
-            // Return the share of fees and pool
-            let mut return_bucket = self.fees.take(self.fees.amount() * share);
-            return_bucket.put(self.pool.take(self.pool.amount() * share));
-            return_bucket
+            // Return the share of the pool
+            self.pool.take(self.pool.amount() * share)
+        }
+
+        // Amount of tokens a single LP token can be removed for, fees released up to now included
+        pub fn share_price(&self) -> Decimal {
+            let supply = borrow_resource_manager!(self.lp_token).total_supply();
+            assert!(!supply.is_zero(), "The pool is empty");
+            (self.pool.amount() + self.releasable_fees()) / supply
+        }
+
+        // Fees earned by a single LP token at every epoch in which fees were released,
+        // covering the epochs since the previous entry
+        pub fn earnings_history(&self) -> Vec<(u64, Decimal)> {
+            self.earnings_history.clone()
+        }
+
+        // Fees not released into the pool yet
+        pub fn locked_fees(&self) -> Decimal {
+            self.fees.amount() - self.releasable_fees()
+        }
+
+        // Fees which should have been released into the pool by now
+        fn releasable_fees(&self) -> Decimal {
+            let now = Runtime::current_epoch();
+            if now >= self.fee_stream_end {
+                self.fees.amount()
+            } else {
+                self.fee_release_rate * (now - self.last_release)
+            }
+        }
+
+        // Move the fees streamed since the last release into the pool
+        fn release_fees(&mut self) {
+            let released = self.releasable_fees();
+            self.last_release = Runtime::current_epoch();
+            if released.is_zero() {
+                return;
+            }
+
+            let supply = borrow_resource_manager!(self.lp_token).total_supply();
+            if !supply.is_zero() {
+                self.earnings_history.push((self.last_release, released / supply));
+            }
+            self.pool.put(self.fees.take(released));
         }
     }
 }
@@ -1,26 +1,62 @@
 use scrypto::prelude::*;
 
+/// Schedule of a lock, carried by its TL badge.
+/// Nothing can be released before the cliff, then the amount unlocks linearly from the start to the end of the lock.
+/// As with a plain lock, the cliff and the end are passed once the current epoch is after them,
+/// so cliff_epoch and end_epoch are the first epochs at which they are passed.
+#[derive(NonFungibleData)]
+pub struct LockSchedule {
+    resource_address: ResourceAddress,
+    amount: Decimal,
+    start_epoch: u64,
+    cliff_epoch: u64,
+    end_epoch: u64,
+    #[scrypto(mutable)]
+    released: Decimal,
+}
+
+impl LockSchedule {
+    /// Amount unlocked at an epoch, released or not
+    fn unlocked(&self, epoch: u64) -> Decimal {
+        if epoch < self.cliff_epoch {
+            Decimal::zero()
+        } else if epoch >= self.end_epoch {
+            self.amount
+        } else {
+            self.amount * (epoch - self.start_epoch) / (self.end_epoch - self.start_epoch)
+        }
+    }
+}
+
+/// Divisibility of a fungible resource, panics on non fungible ones
+fn divisibility(resource_address: ResourceAddress) -> u8 {
+    match borrow_resource_manager!(resource_address).resource_type() {
+        ResourceType::Fungible { divisibility } => divisibility,
+        ResourceType::NonFungible => panic!("Only fungible tokens can be locked"),
+    }
+}
 
 blueprint! {
     struct TimeLock {
         /// Mint authorization to TL badges.
         tl_minter_vault: Vault,
         tl_minter_badge: ResourceAddress,
-        // Minted badges
-        minted: HashMap<ResourceAddress, (Decimal, u64)>,
+        // TL badges, one NFT per lock
+        tl_badge: ResourceAddress,
+        tl_badge_counter: u64,
+
+        // Collected fees, in the locked resources.
+        collected_fees: HashMap<ResourceAddress, Vault>,
 
-        // Collected fees in XRD.
-        collected_fees: Vault,
-        
-        // Locked XRD
-        locked_xrd: Vault,
+        // Locked tokens, one vault per resource
+        locked: HashMap<ResourceAddress, Vault>,
 
         // fee in percents
         fee_percent: Decimal,
     }
 
     impl TimeLock {
-       
+
         pub fn new(fee: Decimal) -> (ComponentAddress, Bucket) {
 
             let mut tl_minter_bucket = ResourceBuilder::new_fungible()
@@ -31,6 +67,13 @@ blueprint! {
             let tl_minter_resource_def = tl_minter_bucket.resource_address();
             let tl_minter_return_bucket: Bucket = tl_minter_bucket.take(1); // Return this badge to the caller
 
+            let tl_badge = ResourceBuilder::new_non_fungible()
+                .metadata("name", "Time lock badge")
+                .mintable(rule!(require(tl_minter_resource_def)), LOCKED)
+                .burnable(rule!(require(tl_minter_resource_def)), LOCKED)
+                .updateable_non_fungible_data(rule!(require(tl_minter_resource_def)), LOCKED)
+                .no_initial_supply();
+
             let access_rules = AccessRules::new()
                 .method("claim", rule!(require(tl_minter_bucket.resource_address())))
                 .default(rule!(allow_all));
@@ -39,9 +82,10 @@ blueprint! {
             let component = Self {
                 tl_minter_vault: Vault::with_bucket(tl_minter_bucket),
                 tl_minter_badge: tl_minter_resource_def,
-                minted: HashMap::new(),
-                collected_fees: Vault::new(RADIX_TOKEN),
-                locked_xrd: Vault::new(RADIX_TOKEN),
+                tl_badge,
+                tl_badge_counter: 0,
+                collected_fees: HashMap::new(),
+                locked: HashMap::new(),
                 fee_percent: fee
             }
             .instantiate();
@@ -49,80 +93,95 @@ blueprint! {
             (component.add_access_check(access_rules).globalize(), tl_minter_return_bucket)
         }
 
-        /// Lock XRD for a certain time.
-        pub fn lock(&mut self, mut lock_tokens: Bucket, duration: u64) -> Bucket{
+        /// Lock fungible tokens for a certain time.
+        /// Nothing can be released before `cliff` epochs, then the tokens unlock linearly until `duration` epochs.
+        pub fn lock(&mut self, mut lock_tokens: Bucket, cliff: u64, duration: u64) -> Bucket{
 
             let amount = lock_tokens.amount();
+            let resource_def = lock_tokens.resource_address();
 
             assert!(amount != Decimal::zero(), "You cannot lock zero amount");
             assert!(duration != 0, "You cannot lock with a zero duration");
+            assert!(cliff <= duration, "The cliff cannot be after the end of the lock");
+            let divisibility = divisibility(resource_def);
+
 
-            
-            // Setup the end time.
-            let end_time = Runtime::current_epoch() + duration;
+            // Setup the schedule.
+            let start_time = Runtime::current_epoch();
 
-            // fees calculation
-            let fee_amount = amount * self.fee_percent/ dec!("100");
+            // fees calculation, rounded down to what the resource can hold
+            let fee_amount = (amount * self.fee_percent/ dec!("100"))
+                .round(divisibility, RoundingMode::TowardsNegativeInfinity);
             // setup fees to be taken from the payment
             let fee_tokens = lock_tokens.take(fee_amount);
 
             let available = lock_tokens.amount();
-            
-            // Put fees in collected XRD.
-            self.collected_fees.put(fee_tokens);
-
-            // Mint TL badge with locked amount and end epoch as metadata
-            let mut tl_resource_def = ResourceBuilder::new_fungible()
-                .metadata("name", "Time lock badge")
-                .metadata("amount", available.to_string())
-                .metadata("ends", end_time.to_string())
-                .mintable(rule!(require(self.tl_minter_vault.resource_address())), LOCKED)
-                .burnable(rule!(require(self.tl_minter_vault.resource_address())), LOCKED)
-                .no_initial_supply();
 
+            // Put fees in collected tokens.
+            self.collected_fees
+                .entry(resource_def)
+                .or_insert_with(|| Vault::new(resource_def))
+                .put(fee_tokens);
+
+            // Mint TL badge with the lock schedule
+            self.tl_badge_counter += 1;
+            let schedule = LockSchedule {
+                resource_address: resource_def,
+                amount: available,
+                start_epoch: start_time,
+                cliff_epoch: start_time + cliff + 1,
+                end_epoch: start_time + duration + 1,
+                released: Decimal::zero(),
+            };
             let tl_badge = self.tl_minter_vault.authorize(|| {
-                borrow_resource_manager!(tl_resource_def).mint(1)
+                borrow_resource_manager!(self.tl_badge)
+                    .mint_non_fungible(&NonFungibleId::from_u64(self.tl_badge_counter), schedule)
             });
 
-            // store new badge address
-            self.minted.insert(tl_resource_def, (available, end_time));
-
             // put the rest amount of tokens to the locked vault
-            self.locked_xrd.put(lock_tokens);
+            self.locked
+                .entry(resource_def)
+                .or_insert_with(|| Vault::new(resource_def))
+                .put(lock_tokens);
             tl_badge
         }
 
-        pub fn release(&mut self, tl_badge: Bucket) -> Bucket {
-            let resource_def = tl_badge.resource_address();
-    
-            let mut returns = Bucket::new(RADIX_TOKEN);
-            match self.minted.get(&resource_def) {
-                Some(&value) => {
-                info!("current epoch {}", Runtime::current_epoch());
-                assert!(Runtime::current_epoch() > value.1, "Release time not yet over, wait for a bit longer"); 
-                assert!(value.0 > Decimal::zero(), "Release amount is zero");
+        /// Release the tokens unlocked so far, can be called again as more of them unlock.
+        /// Returns the tokens along with the TL badge, which is burned once everything has been released.
+        pub fn release(&mut self, tl_badge: Bucket) -> (Bucket, Bucket) {
+            assert!(tl_badge.resource_address() == self.tl_badge, "no mints found with provided badge");
+            assert!(tl_badge.amount() == Decimal::one(), "Release one lock at a time");
+
+            let mut schedule = tl_badge.non_fungible::<LockSchedule>().data();
+            info!("current epoch {}", Runtime::current_epoch());
+            assert!(Runtime::current_epoch() >= schedule.cliff_epoch, "Release time not yet over, wait for a bit longer");
 
+            // Round down to what the resource can hold, the rest is released later on
+            let unlocked = schedule.unlocked(Runtime::current_epoch())
+                .round(divisibility(schedule.resource_address), RoundingMode::TowardsNegativeInfinity);
+            let amount = unlocked - schedule.released;
+            assert!(amount > Decimal::zero(), "Release amount is zero");
+
+            let returns = self.locked.get_mut(&schedule.resource_address).unwrap().take(amount);
+            schedule.released = schedule.released + amount;
+
+            if schedule.released == schedule.amount {
                 // Burn the TL badge
                 self.tl_minter_vault.authorize(|| {
                     tl_badge.burn();
                 });
-                // update mapping
-                self.minted.remove(&resource_def);
-
-                returns.put(self.locked_xrd.take(value.0));
-                },
-                _ => {
-                    info!("no mints found with provided badge");
-                    std::process::abort();
-                }
+                (returns, Bucket::new(self.tl_badge))
+            } else {
+                // update schedule
+                self.tl_minter_vault.authorize(|| {
+                    tl_badge.non_fungible().update_data(schedule);
+                });
+                (returns, tl_badge)
             }
-            
-            // Return the withdrawn tokens
-            returns
         }
 
-        pub fn claim(&mut self) -> Bucket {
-            self.collected_fees.take_all()
+        pub fn claim(&mut self) -> Vec<Bucket> {
+            self.collected_fees.values_mut().map(|fees| fees.take_all()).collect()
         }
     }
-}
\ No newline at end of file
+}
@@ -1,75 +1,14 @@
 use scrypto::prelude::*;
 
-import! { r#"
-{
-  "package_address": "01c7adee40dd9a16ae290272d0e99835ad5c5e679941d3fb28e608",
-  "blueprint_name": "Staking",
-  "functions": [
-    {
-      "name": "new",
-      "inputs": [
-        {
-          "type": "Custom",
-          "name": "Bucket",
-          "generics": []
-        }
-      ],
-      "output": {
-        "type": "Custom",
-        "name": "ComponentAddress",
-        "generics": []
-      }
-    }
-  ],
-  "methods": [
-    {
-      "name": "new_user",
-      "mutability": "Mutable",
-      "inputs": [],
-      "output": {
-        "type": "Custom",
-        "name": "Bucket",
-        "generics": []
-      }
-    },
-    {
-      "name": "stake",
-      "mutability": "Mutable",
-      "inputs": [
-        {
-          "type": "Custom",
-          "name": "Bucket",
-          "generics": []
-        },
-        {
-          "type": "Custom",
-          "name": "Proof",
-          "generics": []
-        }
-      ],
-      "output": {
-        "type": "Unit"
-      }
-    },
-    {
-      "name": "withdraw",
-      "mutability": "Mutable",
-      "inputs": [
-        {
-          "type": "Custom",
-          "name": "Proof",
-          "generics": []
-        }
-      ],
-      "output": {
-        "type": "Custom",
-        "name": "Bucket",
-        "generics": []
-      }
-    }
-  ]
+use bonding_macros::blueprint_stub; // generates a typed stub for calling the Staking component
+
+// the methods of the Staking blueprint used by the lottery
+#[blueprint_stub]
+pub trait Staking {
+    fn new_user(&mut self) -> Bucket;
+    fn stake(&mut self, staking: Bucket, auth: Proof);
+    fn withdraw(&mut self, auth: Proof) -> Bucket;
 }
-"# }
 
 
 #[derive(NonFungibleData)]